@@ -0,0 +1,545 @@
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::{OsStr, OsString};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Seek, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, SystemTime};
+
+use crate::fs_utils::{self, CopyProgress, FileInfo};
+
+// Listings kept around so that redrawing an archive doesn't decompress it again on every key press
+const MAX_CACHED_LISTINGS: usize = 16;
+
+// The archive's modification time and size when it was listed, to notice when it changes
+type CachedListing = (Option<SystemTime>, u64, Arc<Vec<ArchiveEntry>>);
+
+// The start of the members that were previewed, kept for the same reason and checked the same way
+const MAX_CACHED_PREVIEWS: usize = 16;
+type CachedPreview = (Option<SystemTime>, u64, Arc<Vec<u8>>);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+    TarXz,
+    TarZst,
+}
+
+const SUFFIXES: &[(&str, ArchiveKind)] = &[
+    (".zip",     ArchiveKind::Zip),
+    (".tar",     ArchiveKind::Tar),
+    (".tar.gz",  ArchiveKind::TarGz),
+    (".tgz",     ArchiveKind::TarGz),
+    (".tar.xz",  ArchiveKind::TarXz),
+    (".txz",     ArchiveKind::TarXz),
+    (".tar.zst", ArchiveKind::TarZst),
+    (".tzst",    ArchiveKind::TarZst),
+];
+
+impl ArchiveKind {
+    pub fn from_path(path: &Path) -> Option<ArchiveKind> {
+        find_suffix(path).map(|(_, kind)| kind)
+    }
+}
+
+// Matched on the raw bytes, so that a name which isn't valid UTF-8 is still recognised
+fn find_suffix(path: &Path) -> Option<(&'static str, ArchiveKind)> {
+    let name = path.file_name()?.as_encoded_bytes().to_ascii_lowercase();
+    SUFFIXES.iter().find(|(suffix, _)| name.ends_with(suffix.as_bytes())).copied()
+}
+
+/// The name of an archive without its archive suffix, e.g. `src` for `src.tar.gz`.
+pub fn strip_suffix(path: &Path) -> Option<OsString> {
+    let (suffix, _) = find_suffix(path)?;
+    // Each suffix is one or two extensions, which `file_stem` takes off without decoding the name
+    let mut name = Path::new(path.file_name()?);
+    for _ in 0..suffix.matches('.').count() {
+        name = Path::new(name.file_stem()?);
+    }
+    Some(name.as_os_str().to_os_string())
+}
+
+/// A member of an archive, with its path normalised to plain relative components.
+#[derive(Clone, Debug)]
+pub struct ArchiveEntry {
+    pub path: PathBuf,
+    pub is_dir: bool,
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+    pub mode: Option<u32>,
+    // The name as stored in the archive, needed to find the member again
+    raw_name: Vec<u8>,
+}
+
+/// Whether `path` is an archive file that can be browsed like a directory.
+pub fn is_archive(path: &Path) -> bool {
+    ArchiveKind::from_path(path).is_some() && path.is_file()
+}
+
+/// Split a path that points into an archive, like `/tmp/src.tar.gz/src/main.rs`,
+/// into the archive file and the path inside it (empty for the archive itself).
+pub fn split_path(path: &Path) -> Option<(&Path, PathBuf)> {
+    let archive = path.ancestors().find(|ancestor| is_archive(ancestor))?;
+    let inner = path.strip_prefix(archive).ok()?.to_path_buf();
+    Some((archive, inner))
+}
+
+/// The listing of an archive or of a directory inside one, or `None` if `dir` is neither.
+pub fn list_dir(dir: &Path) -> Option<Vec<FileInfo>> {
+    let (archive, inner) = split_path(dir)?;
+    let entries = list_entries(archive).ok()?;
+
+    // Members can be stored without entries for their parent directories, so those are implied from the paths
+    let mut children: BTreeMap<OsString, FileInfo> = BTreeMap::new();
+    for entry in entries.iter() {
+        let Ok(relative) = entry.path.strip_prefix(&inner) else {
+            continue;
+        };
+        let mut components = relative.components();
+        let Some(name) = components.next().map(|component| component.as_os_str()) else {
+            continue;
+        };
+        let is_direct_child = components.next().is_none();
+
+        let file_info = children.entry(name.to_os_string()).or_insert_with(|| FileInfo {
+            name: name.to_os_string(),
+            is_dir: true,
+            ..Default::default()
+        });
+        if is_direct_child {
+            *file_info = create_file_info(name, entry);
+        }
+    }
+
+    let listing: Vec<FileInfo> = children.into_values().collect();
+    // A file path that only looks like it is inside the archive isn't a directory
+    if listing.is_empty() && entries.iter().any(|entry| entry.path == inner && !entry.is_dir) {
+        return None;
+    }
+    Some(listing)
+}
+
+/// Read up to `limit` bytes of a file inside an archive, or `None` if `path` isn't one.
+/// The result is cached while the archive is unchanged.
+pub fn read_file(path: &Path, limit: u64) -> Option<io::Result<Arc<Vec<u8>>>> {
+    static PREVIEWS: OnceLock<Mutex<HashMap<(PathBuf, u64), CachedPreview>>> = OnceLock::new();
+
+    // Anything that exists on disk isn't inside an archive, which spares looking at all of its ancestors
+    if fs::symlink_metadata(path).is_ok() {
+        return None;
+    }
+    let (archive, inner) = split_path(path)?;
+    let metadata = fs::metadata(archive).ok()?;
+    let modified = metadata.modified().ok();
+    let previews = PREVIEWS.get_or_init(Default::default);
+    let key = (path.to_path_buf(), limit);
+
+    if let Some((cached_modified, cached_len, contents)) = previews.lock().unwrap().get(&key) {
+        if *cached_modified == modified && *cached_len == metadata.len() {
+            return Some(Ok(Arc::clone(contents)));
+        }
+    }
+
+    let entries = list_entries(archive).ok()?;
+    let entry = entries.iter().find(|entry| entry.path == inner && !entry.is_dir)?;
+    let contents = match read_member(archive, entry, limit) {
+        Ok(contents) => Arc::new(contents),
+        Err(e) => return Some(Err(e)),
+    };
+
+    let mut previews = previews.lock().unwrap();
+    if previews.len() >= MAX_CACHED_PREVIEWS {
+        previews.clear();
+    }
+    previews.insert(key, (modified, metadata.len(), Arc::clone(&contents)));
+    Some(Ok(contents))
+}
+
+fn read_member(archive: &Path, entry: &ArchiveEntry, limit: u64) -> io::Result<Vec<u8>> {
+    let mut contents = Vec::new();
+    match ArchiveKind::from_path(archive).ok_or_else(unsupported_archive)? {
+        ArchiveKind::Zip => File::open(archive)
+            .and_then(|file| zip::ZipArchive::new(BufReader::new(file)).map_err(io::Error::other))
+            .and_then(|mut zip_archive| {
+                // The zip crate hands out member names as strings, so these are always UTF-8
+                let name = std::str::from_utf8(&entry.raw_name).map_err(io::Error::other)?;
+                let member = zip_archive.by_name(name).map_err(io::Error::other)?;
+                member.take(limit).read_to_end(&mut contents)
+            }),
+        kind => open_tar(archive, kind).and_then(|mut tar_archive| {
+            for member in tar_archive.entries()? {
+                let member = member?;
+                if *member.path_bytes() == *entry.raw_name {
+                    return member.take(limit).read_to_end(&mut contents);
+                }
+            }
+            Err(io::Error::new(io::ErrorKind::NotFound, format!("{} is no longer in the archive", entry.path.display())))
+        }),
+    }?;
+    Ok(contents)
+}
+
+/// All members of an archive, from the cache while the file is unchanged.
+pub fn list_entries(archive: &Path) -> io::Result<Arc<Vec<ArchiveEntry>>> {
+    static LISTINGS: OnceLock<Mutex<HashMap<PathBuf, CachedListing>>> = OnceLock::new();
+
+    let metadata = fs::metadata(archive)?;
+    let modified = metadata.modified().ok();
+    let listings = LISTINGS.get_or_init(Default::default);
+
+    if let Some((cached_modified, cached_len, entries)) = listings.lock().unwrap().get(archive) {
+        if *cached_modified == modified && *cached_len == metadata.len() {
+            return Ok(Arc::clone(entries));
+        }
+    }
+
+    let entries = Arc::new(read_entries(archive)?);
+    let mut listings = listings.lock().unwrap();
+    if listings.len() >= MAX_CACHED_LISTINGS {
+        listings.clear();
+    }
+    listings.insert(archive.to_path_buf(), (modified, metadata.len(), Arc::clone(&entries)));
+    Ok(entries)
+}
+
+fn read_entries(archive: &Path) -> io::Result<Vec<ArchiveEntry>> {
+    let kind = ArchiveKind::from_path(archive)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Not a supported archive"))?;
+
+    let mut entries = Vec::new();
+    match kind {
+        ArchiveKind::Zip => {
+            let mut zip_archive = zip::ZipArchive::new(BufReader::new(File::open(archive)?)).map_err(io::Error::other)?;
+            for index in 0..zip_archive.len() {
+                let member = zip_archive.by_index_raw(index).map_err(io::Error::other)?;
+                let Some(path) = normalize(Path::new(member.name())) else {
+                    continue;
+                };
+                let time = member.last_modified();
+                entries.push(ArchiveEntry {
+                    path,
+                    is_dir: member.is_dir(),
+                    size: member.size(),
+                    modified: zip_time(time.year(), time.month(), time.day(), time.hour(), time.minute(), time.second()),
+                    mode: member.unix_mode(),
+                    raw_name: member.name().as_bytes().to_vec(),
+                });
+            }
+        }
+        kind => {
+            let mut tar_archive = open_tar(archive, kind)?;
+            for member in tar_archive.entries()? {
+                let member = member?;
+                let header = member.header();
+                let Some(path) = normalize(&member.path()?) else {
+                    continue;
+                };
+                entries.push(ArchiveEntry {
+                    path,
+                    is_dir: header.entry_type().is_dir(),
+                    size: header.size().unwrap_or(0),
+                    modified: header.mtime().ok().map(|seconds| SystemTime::UNIX_EPOCH + Duration::from_secs(seconds)),
+                    mode: header.mode().ok(),
+                    raw_name: member.path_bytes().into_owned(),
+                });
+            }
+        }
+    }
+    Ok(entries)
+}
+
+/// Total uncompressed size and number of files in an archive, for reporting extraction progress.
+pub fn unpacked_size(archive: &Path) -> (u64, u64) {
+    list_entries(archive)
+        .map(|entries| {
+            entries.iter()
+                .filter(|entry| !entry.is_dir)
+                .fold((0, 0), |(bytes, files), entry| (bytes + entry.size, files + 1))
+        })
+        .unwrap_or((0, 0))
+}
+
+/// Pack `sources` into a new archive at `dest`, in the format its name asks for. Directories are
+/// stored with everything under them and symlinks are stored as links.
+pub fn create_archive(sources: &[PathBuf], dest: &Path, progress: &mut CopyProgress) -> io::Result<()> {
+    let kind = ArchiveKind::from_path(dest).ok_or_else(unsupported_archive)?;
+    let file = BufWriter::new(File::create(dest)?);
+
+    let file = match kind {
+        ArchiveKind::Zip => {
+            let mut writer = zip::ZipWriter::new(file);
+            for src in sources {
+                add_to_zip(&mut writer, src, &file_name(src)?, progress)?;
+            }
+            writer.finish().map_err(io::Error::other)?
+        }
+        ArchiveKind::Tar    => write_tar(file, sources, progress)?,
+        ArchiveKind::TarGz  => write_tar(flate2::write::GzEncoder::new(file, flate2::Compression::default()), sources, progress)?.finish()?,
+        ArchiveKind::TarXz  => write_tar(xz2::write::XzEncoder::new(file, 6), sources, progress)?.finish()?,
+        ArchiveKind::TarZst => write_tar(zstd::stream::write::Encoder::new(file, 0)?, sources, progress)?.finish()?,
+    };
+    file.into_inner().map_err(|e| e.into_error())?.sync_all()
+}
+
+/// Unpack an archive into `dest`, which must not exist yet. Members that would end up outside of
+/// it, through `..` or through a symlink unpacked earlier, are refused.
+pub fn extract_archive(archive: &Path, dest: &Path, progress: &mut CopyProgress) -> io::Result<()> {
+    let kind = ArchiveKind::from_path(archive).ok_or_else(unsupported_archive)?;
+    fs::create_dir(dest)?;
+
+    match kind {
+        ArchiveKind::Zip => {
+            let mut zip_archive = zip::ZipArchive::new(BufReader::new(File::open(archive)?)).map_err(io::Error::other)?;
+            for index in 0..zip_archive.len() {
+                let mut member = zip_archive.by_index(index).map_err(io::Error::other)?;
+                let Some(relative) = member.enclosed_name().map(Path::to_path_buf) else {
+                    continue;
+                };
+                if member.is_dir() {
+                    create_dirs_inside(dest, &relative)?;
+                    continue;
+                }
+
+                if let Some(parent) = relative.parent() {
+                    create_dirs_inside(dest, parent)?;
+                }
+                unpack_zip_member(&mut member, &dest.join(relative))?;
+                fs_utils::report_progress(progress, member.size(), 1)?;
+            }
+        }
+        kind => {
+            let mut tar_archive = open_tar(archive, kind)?;
+            tar_archive.set_preserve_mtime(true);
+            for member in tar_archive.entries()? {
+                let mut member = member?;
+                let header = member.header();
+                let is_file = !header.entry_type().is_dir();
+                let size = header.size().unwrap_or(0);
+                member.unpack_in(dest)?;
+                if is_file {
+                    fs_utils::report_progress(progress, size, 1)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn unsupported_archive() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, "Not a supported archive")
+}
+
+fn file_name(path: &Path) -> io::Result<PathBuf> {
+    path.file_name()
+        .map(PathBuf::from)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("{} has no name", path.display())))
+}
+
+fn write_tar<W: Write>(writer: W, sources: &[PathBuf], progress: &mut CopyProgress) -> io::Result<W> {
+    let mut builder = tar::Builder::new(writer);
+    builder.follow_symlinks(false);
+    for src in sources {
+        add_to_tar(&mut builder, src, &file_name(src)?, progress)?;
+    }
+    builder.into_inner()
+}
+
+fn add_to_tar<W: Write>(builder: &mut tar::Builder<W>, src: &Path, name: &Path, progress: &mut CopyProgress) -> io::Result<()> {
+    let metadata = fs::symlink_metadata(src)?;
+    if !metadata.is_dir() {
+        builder.append_path_with_name(src, name)?;
+        return fs_utils::report_progress(progress, metadata.len(), 1);
+    }
+
+    builder.append_dir(name, src)?;
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        add_to_tar(builder, &entry.path(), &name.join(entry.file_name()), progress)?;
+    }
+    Ok(())
+}
+
+fn add_to_zip<W: Write + Seek>(writer: &mut zip::ZipWriter<W>, src: &Path, name: &Path, progress: &mut CopyProgress) -> io::Result<()> {
+    let metadata = fs::symlink_metadata(src)?;
+    // Zip names always use forward slashes
+    let zip_name = name.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+
+    let mut options = zip::write::FileOptions::default().large_file(metadata.len() >= u32::MAX as u64);
+    if let Some(mode) = file_mode(&metadata) {
+        options = options.unix_permissions(mode & 0o7777);
+    }
+    if let Some(time) = metadata.modified().ok().and_then(zip_date_time) {
+        options = options.last_modified_time(time);
+    }
+
+    if metadata.is_dir() {
+        writer.add_directory(zip_name, options).map_err(io::Error::other)?;
+        for entry in fs::read_dir(src)? {
+            let entry = entry?;
+            add_to_zip(writer, &entry.path(), &name.join(entry.file_name()), progress)?;
+        }
+        return Ok(());
+    }
+
+    if metadata.is_symlink() {
+        let target = fs::read_link(src)?;
+        writer.add_symlink(zip_name, target.to_string_lossy(), options).map_err(io::Error::other)?;
+    } else {
+        writer.start_file(zip_name, options).map_err(io::Error::other)?;
+        io::copy(&mut File::open(src)?, writer)?;
+    }
+    fs_utils::report_progress(progress, metadata.len(), 1)
+}
+
+#[cfg(unix)]
+fn unpack_zip_member(member: &mut zip::read::ZipFile, target: &Path) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    const FILE_TYPE_MASK: u32 = 0o170000;
+    const SYMLINK: u32 = 0o120000;
+
+    let mode = member.unix_mode();
+    if mode.is_some_and(|mode| mode & FILE_TYPE_MASK == SYMLINK) {
+        let mut link_target = String::new();
+        member.read_to_string(&mut link_target)?;
+        return std::os::unix::fs::symlink(link_target, target);
+    }
+
+    io::copy(member, &mut create_new_file(target)?)?;
+    if let Some(mode) = mode {
+        fs::set_permissions(target, fs::Permissions::from_mode(mode & 0o7777))?;
+    }
+    set_zip_mtime(member, target)
+}
+
+#[cfg(windows)]
+fn unpack_zip_member(member: &mut zip::read::ZipFile, target: &Path) -> io::Result<()> {
+    io::copy(member, &mut create_new_file(target)?)?;
+    set_zip_mtime(member, target)
+}
+
+fn set_zip_mtime(member: &zip::read::ZipFile, target: &Path) -> io::Result<()> {
+    let time = member.last_modified();
+    match zip_time(time.year(), time.month(), time.day(), time.hour(), time.minute(), time.second()) {
+        Some(modified) => filetime::set_file_mtime(target, filetime::FileTime::from_system_time(modified)),
+        None => Ok(()),
+    }
+}
+
+// A symlink unpacked earlier could point a later member anywhere on the system, so directories
+// are created one level at a time and never through a symlink
+fn create_dirs_inside(dest: &Path, relative: &Path) -> io::Result<()> {
+    let mut path = dest.to_path_buf();
+    for component in relative.components() {
+        path.push(component);
+        match fs::symlink_metadata(&path) {
+            Ok(metadata) if metadata.is_symlink() => return Err(points_outside(&path)),
+            Ok(_) => {}
+            Err(e) if e.kind() == io::ErrorKind::NotFound => fs::create_dir(&path)?,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+// Never opens an existing file, which could be a symlink to somewhere outside of the archive
+fn create_new_file(target: &Path) -> io::Result<File> {
+    fs::OpenOptions::new().write(true).create_new(true).open(target).map_err(|e| {
+        if e.kind() == io::ErrorKind::AlreadyExists && target.is_symlink() {
+            points_outside(target)
+        } else {
+            e
+        }
+    })
+}
+
+fn points_outside(path: &Path) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("{} points outside of the archive", path.display()))
+}
+
+#[cfg(unix)]
+fn file_mode(metadata: &fs::Metadata) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+    Some(metadata.permissions().mode())
+}
+
+#[cfg(windows)]
+fn file_mode(_metadata: &fs::Metadata) -> Option<u32> {
+    None
+}
+
+fn open_tar(archive: &Path, kind: ArchiveKind) -> io::Result<tar::Archive<Box<dyn Read>>> {
+    let file = BufReader::new(File::open(archive)?);
+    let reader: Box<dyn Read> = match kind {
+        ArchiveKind::TarGz  => Box::new(flate2::read::GzDecoder::new(file)),
+        ArchiveKind::TarXz  => Box::new(xz2::read::XzDecoder::new(file)),
+        ArchiveKind::TarZst => Box::new(zstd::stream::read::Decoder::with_buffer(file)?),
+        _                   => Box::new(file),
+    };
+    Ok(tar::Archive::new(reader))
+}
+
+// Drop "." and leading "/" components; paths that climb out with ".." can't be shown inside the archive
+fn normalize(path: &Path) -> Option<PathBuf> {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => normalized.push(name),
+            Component::CurDir | Component::RootDir | Component::Prefix(_) => {}
+            Component::ParentDir => return None,
+        }
+    }
+    (!normalized.as_os_str().is_empty()).then_some(normalized)
+}
+
+// Zip stores local wall-clock time without a time zone
+fn zip_time(year: u16, month: u8, day: u8, hour: u8, minute: u8, second: u8) -> Option<SystemTime> {
+    use chrono::TimeZone;
+
+    let date = chrono::NaiveDate::from_ymd_opt(year.into(), month.into(), day.into())?;
+    let local = date.and_hms_opt(hour.into(), minute.into(), second.into())?;
+    chrono::Local.from_local_datetime(&local).earliest().map(SystemTime::from)
+}
+
+fn zip_date_time(time: SystemTime) -> Option<zip::DateTime> {
+    use chrono::{Datelike, Timelike};
+
+    let local = chrono::DateTime::<chrono::Local>::from(time);
+    zip::DateTime::from_date_and_time(
+        local.year().try_into().ok()?,
+        local.month() as u8,
+        local.day() as u8,
+        local.hour() as u8,
+        local.minute() as u8,
+        local.second() as u8,
+    ).ok()
+}
+
+fn create_file_info(name: &OsStr, entry: &ArchiveEntry) -> FileInfo {
+    let perms = entry.mode.and_then(permissions_from_mode);
+    FileInfo {
+        name: name.to_os_string(),
+        is_exec: !entry.is_dir && perms.as_ref().is_some_and(crate::fs_utils::is_executable),
+        perms,
+        is_dir: entry.is_dir,
+        size: entry.size,
+        modified: entry.modified,
+        ..Default::default()
+    }
+}
+
+#[cfg(unix)]
+fn permissions_from_mode(mode: u32) -> Option<fs::Permissions> {
+    use std::os::unix::fs::PermissionsExt;
+    Some(fs::Permissions::from_mode(mode))
+}
+
+#[cfg(windows)]
+fn permissions_from_mode(_mode: u32) -> Option<fs::Permissions> {
+    None
+}
@@ -0,0 +1,90 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::fs_utils::{data_home, decode_path, encode_path};
+
+/// Directories saved under a letter, kept in a file so they survive restarts.
+#[derive(Default)]
+pub struct Bookmarks {
+    file: Option<PathBuf>,
+    marks: BTreeMap<char, PathBuf>,
+}
+
+impl Bookmarks {
+    /// The bookmarks stored in `file`. A file that doesn't exist yet holds no bookmarks.
+    pub fn open(file: &Path) -> io::Result<Self> {
+        let contents = match fs::read_to_string(file) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e),
+        };
+
+        // One bookmark per line: the letter, a space and the escaped path. Unreadable lines are dropped.
+        let marks = contents
+            .lines()
+            .filter_map(|line| {
+                let mut chars = line.chars();
+                let letter = chars.next().filter(|letter| is_bookmark_letter(*letter))?;
+                let path = chars.as_str().strip_prefix(' ')?;
+                Some((letter, decode_path(path)))
+            })
+            .collect();
+
+        Ok(Bookmarks { file: Some(file.to_path_buf()), marks })
+    }
+
+    /// The bookmarks in `$XDG_DATA_HOME/changer/bookmarks`, falling back to `~/.local/share/changer/bookmarks`.
+    pub fn home() -> io::Result<Self> {
+        Bookmarks::open(&data_home()?.join("changer").join("bookmarks"))
+    }
+
+    pub fn get(&self, letter: char) -> Option<&Path> {
+        self.marks.get(&letter).map(PathBuf::as_path)
+    }
+
+    /// All bookmarks in letter order.
+    pub fn iter(&self) -> impl Iterator<Item = (char, &Path)> {
+        self.marks.iter().map(|(letter, path)| (*letter, path.as_path()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.marks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.marks.is_empty()
+    }
+
+    /// Save `dir` under `letter`, replacing what was there.
+    pub fn set(&mut self, letter: char, dir: &Path) -> io::Result<()> {
+        self.marks.insert(letter, dir.to_path_buf());
+        self.save()
+    }
+
+    pub fn remove(&mut self, letter: char) -> io::Result<()> {
+        self.marks.remove(&letter);
+        self.save()
+    }
+
+    // Written to a temporary file first, so a crash halfway can't lose the existing bookmarks
+    fn save(&self) -> io::Result<()> {
+        let file = self.file.as_ref()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Could not locate the data directory"))?;
+        if let Some(parent) = file.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let contents: String = self.marks.iter()
+            .map(|(letter, path)| format!("{} {}\n", letter, encode_path(path)))
+            .collect();
+        let temporary = file.with_extension("tmp");
+        fs::write(&temporary, contents)?;
+        fs::rename(&temporary, file)
+    }
+}
+
+pub fn is_bookmark_letter(c: char) -> bool {
+    c.is_ascii_alphabetic()
+}
@@ -0,0 +1,520 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crossterm::event::{KeyCode, KeyModifiers};
+use tui::style::Color;
+
+use crate::sort::{SortMode, SortOptions};
+use crate::filter::FileFilter;
+
+/// Everything that can be bound to a key in normal mode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    MoveDown,
+    MoveUp,
+    MoveIn,
+    MoveOut,
+    GoBack,
+    GoForward,
+    HalfPageDown,
+    HalfPageUp,
+    GoToTop,
+    GoToBottom,
+    NewTab,
+    DuplicateTab,
+    CloseTab,
+    NextTab,
+    PreviousTab,
+    ToggleDualPane,
+    SwitchPanel,
+    CopyToOtherPanel,
+    MoveToOtherPanel,
+    PreviewDown,
+    PreviewUp,
+    PreviewHalfPageDown,
+    PreviewHalfPageUp,
+    PreviewTop,
+    PreviewBottom,
+    Search,
+    NextMatch,
+    PreviousMatch,
+    CycleSort,
+    ReverseSort,
+    ToggleDirsFirst,
+    ToggleHidden,
+    ToggleGitignore,
+    ToggleFollowSymlinks,
+    ToggleMark,
+    MarkAll,
+    InvertMarks,
+    ClearMarks,
+    Copy,
+    Cut,
+    Paste,
+    SetBookmark,
+    JumpToBookmark,
+    BookmarksView,
+    PasteSymlink,
+    PasteRelativeSymlink,
+    PasteHardLink,
+    Delete,
+    PermanentDelete,
+    TrashView,
+    Rename,
+    CreateFile,
+    CreateDir,
+    ChangePermissions,
+    Compress,
+    Extract,
+    JobsView,
+    Undo,
+    Redo,
+    Quit,
+}
+
+// The name of every action in the `[keys]` table along with its default bindings
+const ACTIONS: &[(&str, Action, &[&str])] = &[
+    ("move_down",              Action::MoveDown,            &["j"]),
+    ("move_up",                Action::MoveUp,              &["k"]),
+    ("move_in",                Action::MoveIn,              &["l"]),
+    ("move_out",               Action::MoveOut,             &["h"]),
+    ("go_back",                Action::GoBack,              &["H"]),
+    ("go_forward",             Action::GoForward,           &["L"]),
+    ("half_page_down",         Action::HalfPageDown,        &["<C-d>"]),
+    ("half_page_up",           Action::HalfPageUp,          &["<C-u>"]),
+    ("go_to_top",              Action::GoToTop,             &["gg"]),
+    ("go_to_bottom",           Action::GoToBottom,          &["G"]),
+    ("new_tab",                Action::NewTab,              &["gn"]),
+    ("duplicate_tab",          Action::DuplicateTab,        &["gd"]),
+    ("close_tab",              Action::CloseTab,            &["gc"]),
+    ("next_tab",               Action::NextTab,             &["gt"]),
+    ("previous_tab",           Action::PreviousTab,         &["gT"]),
+    ("toggle_dual_pane",       Action::ToggleDualPane,      &["zd"]),
+    ("switch_panel",           Action::SwitchPanel,         &["<Tab>"]),
+    ("preview_down",           Action::PreviewDown,         &["<A-j>"]),
+    ("preview_up",             Action::PreviewUp,           &["<A-k>"]),
+    ("preview_half_page_down", Action::PreviewHalfPageDown, &["<A-d>"]),
+    ("preview_half_page_up",   Action::PreviewHalfPageUp,   &["<A-u>"]),
+    ("preview_top",            Action::PreviewTop,          &["<A-g><A-g>"]),
+    ("preview_bottom",         Action::PreviewBottom,       &["<A-G>"]),
+    ("search",                 Action::Search,              &["/"]),
+    ("next_match",             Action::NextMatch,           &["n"]),
+    ("previous_match",         Action::PreviousMatch,       &["N"]),
+    ("cycle_sort",             Action::CycleSort,           &["s"]),
+    ("reverse_sort",           Action::ReverseSort,         &["S"]),
+    ("toggle_dirs_first",      Action::ToggleDirsFirst,     &["F"]),
+    ("toggle_hidden",          Action::ToggleHidden,        &["zh"]),
+    ("toggle_gitignore",       Action::ToggleGitignore,     &["zi"]),
+    ("toggle_follow_symlinks", Action::ToggleFollowSymlinks, &["zl"]),
+    ("toggle_mark",            Action::ToggleMark,          &["<Space>"]),
+    ("mark_all",               Action::MarkAll,             &["V"]),
+    ("invert_marks",           Action::InvertMarks,         &["v"]),
+    ("clear_marks",            Action::ClearMarks,          &["<Esc>"]),
+    ("set_bookmark",           Action::SetBookmark,         &["m"]),
+    ("jump_to_bookmark",       Action::JumpToBookmark,      &["'"]),
+    ("bookmarks_view",         Action::BookmarksView,       &["M"]),
+    ("copy",                   Action::Copy,                &["y"]),
+    ("cut",                    Action::Cut,                 &["x"]),
+    ("paste",                  Action::Paste,               &["p"]),
+    ("copy_to_other_panel",    Action::CopyToOtherPanel,    &["<F5>"]),
+    ("move_to_other_panel",    Action::MoveToOtherPanel,    &["<F6>"]),
+    ("paste_symlink",          Action::PasteSymlink,        &["Pl"]),
+    ("paste_relative_symlink", Action::PasteRelativeSymlink, &["PL"]),
+    ("paste_hard_link",        Action::PasteHardLink,       &["Ph"]),
+    ("delete",                 Action::Delete,              &["D"]),
+    ("permanent_delete",       Action::PermanentDelete,     &["X"]),
+    ("trash_view",             Action::TrashView,           &["T"]),
+    ("rename",                 Action::Rename,              &["r"]),
+    ("create_file",            Action::CreateFile,          &["a"]),
+    ("create_dir",             Action::CreateDir,           &["A"]),
+    ("change_permissions",     Action::ChangePermissions,   &["c"]),
+    ("compress",               Action::Compress,            &["C"]),
+    ("extract",                Action::Extract,             &["E"]),
+    ("jobs_view",              Action::JobsView,            &["J"]),
+    ("undo",                   Action::Undo,                &["u"]),
+    ("redo",                   Action::Redo,                &["<C-r>"]),
+    ("quit",                   Action::Quit,                &["q"]),
+];
+
+/// A single key press. Shift is folded into the character for printable keys, so `G` and `<S-g>` are the same press.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyPress {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyPress {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        match code {
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => KeyPress {
+                code: KeyCode::Char(c.to_ascii_uppercase()),
+                modifiers: modifiers - KeyModifiers::SHIFT,
+            },
+            _ => KeyPress { code, modifiers },
+        }
+    }
+}
+
+/// Parse a sequence such as `gg`, `<C-d>` or `<A-g><A-g>`. Special keys are written in angle brackets
+/// (`<Space>`, `<Esc>`, `<Enter>`, `<lt>` for a literal `<`, ...), optionally prefixed by `C-`, `A-` and `S-`.
+pub fn parse_key_sequence(sequence: &str) -> Result<Vec<KeyPress>, String> {
+    let mut keys = Vec::new();
+    let mut rest = sequence;
+
+    while let Some(c) = rest.chars().next() {
+        if c == '<' {
+            let end = rest.find('>').ok_or_else(|| format!("unclosed \"<\" in \"{}\"", sequence))?;
+            // "<C->>" binds Ctrl and the ">" key itself
+            let end = if rest[end + 1..].starts_with('>') && rest[..end].ends_with('-') { end + 1 } else { end };
+            keys.push(parse_special_key(&rest[1..end])?);
+            rest = &rest[end + 1..];
+        } else {
+            keys.push(KeyPress::new(KeyCode::Char(c), KeyModifiers::NONE));
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+
+    if keys.is_empty() {
+        return Err("empty key sequence".to_string());
+    }
+    Ok(keys)
+}
+
+fn parse_special_key(key: &str) -> Result<KeyPress, String> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut name = key;
+    while name.len() > 2 && name.as_bytes()[1] == b'-' {
+        modifiers |= match name.as_bytes()[0].to_ascii_uppercase() {
+            b'C' => KeyModifiers::CONTROL,
+            b'A' | b'M' => KeyModifiers::ALT,
+            b'S' => KeyModifiers::SHIFT,
+            _ => return Err(format!("unknown modifier in \"<{}>\"", key)),
+        };
+        name = &name[2..];
+    }
+
+    let mut chars = name.chars();
+    let code = match (chars.next(), chars.next()) {
+        (Some(c), None) => KeyCode::Char(c),
+        _ => match name.to_ascii_lowercase().as_str() {
+            "space"     => KeyCode::Char(' '),
+            "lt"        => KeyCode::Char('<'),
+            "esc"       => KeyCode::Esc,
+            "enter" | "cr" => KeyCode::Enter,
+            "tab"       => KeyCode::Tab,
+            "backspace" | "bs" => KeyCode::Backspace,
+            "del" | "delete" => KeyCode::Delete,
+            "up"        => KeyCode::Up,
+            "down"      => KeyCode::Down,
+            "left"      => KeyCode::Left,
+            "right"     => KeyCode::Right,
+            "home"      => KeyCode::Home,
+            "end"       => KeyCode::End,
+            "pageup"    => KeyCode::PageUp,
+            "pagedown"  => KeyCode::PageDown,
+            lower => match lower.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                Some(n) if (1..=12).contains(&n) => KeyCode::F(n),
+                _ => return Err(format!("unknown key \"<{}>\"", key)),
+            },
+        },
+    };
+    Ok(KeyPress::new(code, modifiers))
+}
+
+pub enum KeyLookup {
+    Action(Action),
+    /// The keys so far are the start of a longer binding.
+    Pending,
+    Unbound,
+}
+
+pub struct Keymap {
+    bindings: Vec<(Vec<KeyPress>, Action)>,
+}
+
+impl Keymap {
+    pub fn lookup(&self, keys: &[KeyPress]) -> KeyLookup {
+        if let Some((_, action)) = self.bindings.iter().find(|(sequence, _)| sequence == keys) {
+            KeyLookup::Action(*action)
+        } else if self.bindings.iter().any(|(sequence, _)| sequence.starts_with(keys)) {
+            KeyLookup::Pending
+        } else {
+            KeyLookup::Unbound
+        }
+    }
+
+    fn bind(&mut self, name: &str, value: &toml::Value) -> Result<(), String> {
+        let action = ACTIONS.iter()
+            .find(|(action_name, _, _)| *action_name == name)
+            .map(|(_, action, _)| *action)
+            .ok_or_else(|| "unknown action".to_string())?;
+
+        let sequences: Vec<&str> = match value {
+            toml::Value::String(sequence) => vec![sequence.as_str()],
+            toml::Value::Array(values) => values.iter()
+                .map(|value| value.as_str().ok_or_else(|| "expected a key sequence or a list of them".to_string()))
+                .collect::<Result<_, _>>()?,
+            _ => return Err("expected a key sequence or a list of them".to_string()),
+        };
+
+        let mut bindings = Vec::new();
+        for sequence in sequences {
+            let keys = parse_key_sequence(sequence)?;
+            if let Some(other) = self.conflict(&keys) {
+                return Err(format!("\"{}\" clashes with a key bound to {}", sequence, action_name(other)));
+            }
+            bindings.push((keys, action));
+        }
+        self.bindings.extend(bindings);
+        Ok(())
+    }
+
+    // A binding that is a prefix of another would shadow it, since bindings fire as soon as they match
+    fn conflict(&self, keys: &[KeyPress]) -> Option<Action> {
+        self.bindings.iter()
+            .find(|(sequence, _)| sequence.starts_with(keys) || keys.starts_with(sequence))
+            .map(|(_, action)| *action)
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bindings = ACTIONS.iter()
+            .flat_map(|(_, action, defaults)| {
+                defaults.iter().map(move |sequence| (parse_key_sequence(sequence).unwrap(), *action))
+            })
+            .collect();
+        Keymap { bindings }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Theme {
+    pub directory: Color,
+    pub executable: Color,
+    pub symlink: Color,
+    pub broken_symlink: Color,
+    pub file: Color,
+    pub marked: Color,
+    pub selected_background: Color,
+    pub progress: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            directory: Color::Blue,
+            executable: Color::Green,
+            symlink: Color::Cyan,
+            broken_symlink: Color::Red,
+            file: Color::White,
+            marked: Color::Yellow,
+            selected_background: Color::Black,
+            progress: Color::Green,
+        }
+    }
+}
+
+/// A colour name such as `blue` or `light_red`, a `#rrggbb` hex value or a 256-colour palette index.
+pub fn parse_color(value: &toml::Value) -> Result<Color, String> {
+    if let Some(index) = value.as_integer() {
+        return u8::try_from(index)
+            .map(Color::Indexed)
+            .map_err(|_| format!("colour index {} is not between 0 and 255", index));
+    }
+
+    let name = value.as_str().ok_or_else(|| "expected a colour name, \"#rrggbb\" or a number".to_string())?;
+    if let Some(hex) = name.strip_prefix('#') {
+        let rgb = u32::from_str_radix(hex, 16).ok().filter(|_| hex.len() == 6)
+            .ok_or_else(|| format!("invalid hex colour \"{}\"", name))?;
+        return Ok(Color::Rgb((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8));
+    }
+
+    Ok(match name.to_ascii_lowercase().replace('-', "_").as_str() {
+        "reset"         => Color::Reset,
+        "black"         => Color::Black,
+        "red"           => Color::Red,
+        "green"         => Color::Green,
+        "yellow"        => Color::Yellow,
+        "blue"          => Color::Blue,
+        "magenta"       => Color::Magenta,
+        "cyan"          => Color::Cyan,
+        "gray" | "grey" => Color::Gray,
+        "dark_gray" | "dark_grey" => Color::DarkGray,
+        "light_red"     => Color::LightRed,
+        "light_green"   => Color::LightGreen,
+        "light_yellow"  => Color::LightYellow,
+        "light_blue"    => Color::LightBlue,
+        "light_magenta" => Color::LightMagenta,
+        "light_cyan"    => Color::LightCyan,
+        "white"         => Color::White,
+        _ => return Err(format!("unknown colour \"{}\"", name)),
+    })
+}
+
+fn parse_sort_mode(name: &str) -> Result<SortMode, String> {
+    match name {
+        "name"      => Ok(SortMode::Name),
+        "size"      => Ok(SortMode::Size),
+        "modified"  => Ok(SortMode::Modified),
+        "changed"   => Ok(SortMode::Changed),
+        "extension" => Ok(SortMode::Extension),
+        "type"      => Ok(SortMode::Type),
+        _ => Err(format!("unknown sort mode \"{}\" (expected name, size, modified, changed, extension or type)", name)),
+    }
+}
+
+pub struct Config {
+    pub keymap: Keymap,
+    pub theme: Theme,
+    /// Relative widths of the parent, current and preview panes.
+    pub pane_ratios: [u32; 3],
+    pub sort_options: SortOptions,
+    pub filter: FileFilter,
+    /// Whether file operations act on what symlinks point to instead of the links themselves.
+    pub follow_symlinks: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            keymap: Keymap::default(),
+            theme: Theme::default(),
+            pane_ratios: [20, 30, 50],
+            sort_options: SortOptions::default(),
+            filter: FileFilter::default(),
+            follow_symlinks: false,
+        }
+    }
+}
+
+impl Config {
+    /// `$XDG_CONFIG_HOME/changer/config.toml`, falling back to `~/.config/changer/config.toml`.
+    pub fn path() -> Option<PathBuf> {
+        env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+            .map(|config_home| config_home.join("changer").join("config.toml"))
+    }
+
+    /// Load the user's config. A missing file gives the defaults; invalid entries are left at their
+    /// defaults and reported alongside the config so the rest of the file still applies.
+    pub fn load() -> (Config, Vec<String>) {
+        let Some(path) = Config::path() else {
+            return (Config::default(), Vec::new());
+        };
+
+        match fs::read_to_string(&path) {
+            Ok(contents) => Config::parse(&contents),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => (Config::default(), Vec::new()),
+            Err(e) => (Config::default(), vec![format!("{}: {}", path.display(), e)]),
+        }
+    }
+
+    pub fn parse(contents: &str) -> (Config, Vec<String>) {
+        let mut config = Config::default();
+        let mut errors = Vec::new();
+
+        let table = match contents.parse::<toml::Value>() {
+            Ok(toml::Value::Table(table)) => table,
+            Ok(_) => return (config, vec!["expected a table at the top level".to_string()]),
+            Err(e) => return (config, vec![e.to_string()]),
+        };
+
+        let mut user_keymap = Keymap { bindings: Vec::new() };
+        for (section, value) in &table {
+            let Some(entries) = value.as_table() else {
+                errors.push(format!("{}: expected a table", section));
+                continue;
+            };
+
+            for (key, value) in entries {
+                let result = match section.as_str() {
+                    "keys"    => user_keymap.bind(key, value),
+                    "colors"  => config.set_color(key, value),
+                    "layout"  => config.set_layout(key, value),
+                    "options" => config.set_option(key, value),
+                    _ => Err("unknown section".to_string()),
+                };
+                if let Err(e) = result {
+                    errors.push(format!("{}.{}: {}", section, key, e));
+                }
+            }
+        }
+
+        // Bindings from the file replace the action's defaults, along with any default bindings they clash with
+        config.keymap.bindings.retain(|(sequence, action)| {
+            user_keymap.bindings.iter().all(|(_, user_action)| user_action != action)
+                && user_keymap.conflict(sequence).is_none()
+        });
+        config.keymap.bindings.extend(user_keymap.bindings);
+
+        (config, errors)
+    }
+
+    fn set_color(&mut self, name: &str, value: &toml::Value) -> Result<(), String> {
+        let color = parse_color(value)?;
+        let field = match name {
+            "directory"           => &mut self.theme.directory,
+            "executable"          => &mut self.theme.executable,
+            "symlink"             => &mut self.theme.symlink,
+            "broken_symlink"      => &mut self.theme.broken_symlink,
+            "file"                => &mut self.theme.file,
+            "marked"              => &mut self.theme.marked,
+            "selected_background" => &mut self.theme.selected_background,
+            "progress"            => &mut self.theme.progress,
+            _ => return Err("unknown colour".to_string()),
+        };
+        *field = color;
+        Ok(())
+    }
+
+    fn set_layout(&mut self, name: &str, value: &toml::Value) -> Result<(), String> {
+        match name {
+            "pane_ratios" => {
+                let invalid = || "expected three non-negative numbers, e.g. [20, 30, 50]".to_string();
+                let values = value.as_array().filter(|values| values.len() == 3).ok_or_else(invalid)?;
+                let mut ratios = [0; 3];
+                for (ratio, value) in ratios.iter_mut().zip(values) {
+                    *ratio = value.as_integer().and_then(|n| u32::try_from(n).ok()).ok_or_else(invalid)?;
+                }
+                if ratios.iter().all(|&ratio| ratio == 0) {
+                    return Err("at least one pane needs a non-zero width".to_string());
+                }
+                self.pane_ratios = ratios;
+                Ok(())
+            }
+            _ => Err("unknown option".to_string()),
+        }
+    }
+
+    fn set_option(&mut self, name: &str, value: &toml::Value) -> Result<(), String> {
+        let expect_bool = || value.as_bool().ok_or_else(|| "expected true or false".to_string());
+        match name {
+            "sort" => {
+                let mode = value.as_str().ok_or_else(|| "expected a string".to_string())?;
+                self.sort_options.mode = parse_sort_mode(mode)?;
+            }
+            "sort_reverse" => self.sort_options.reverse = expect_bool()?,
+            "dirs_first"   => self.sort_options.dirs_first = expect_bool()?,
+            "show_hidden"  => self.filter.show_hidden = expect_bool()?,
+            "gitignore"    => self.filter.respect_gitignore = expect_bool()?,
+            "follow_symlinks" => self.follow_symlinks = expect_bool()?,
+            "ignore" => {
+                let patterns: Vec<&str> = value.as_array()
+                    .and_then(|values| values.iter().map(|value| value.as_str()).collect())
+                    .ok_or_else(|| "expected a list of glob patterns".to_string())?;
+                self.filter.set_ignore_globs(&patterns)?;
+            }
+            _ => return Err("unknown option".to_string()),
+        }
+        Ok(())
+    }
+}
+
+fn action_name(action: Action) -> &'static str {
+    ACTIONS.iter().find(|(_, other, _)| *other == action).map(|(name, _, _)| *name).unwrap_or("an action")
+}
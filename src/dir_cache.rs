@@ -0,0 +1,135 @@
+use std::path::Path;
+
+use crate::fs_utils::FileInfo;
+
+/// The listing of `dir`, produced by `read` unless it is cached. Listings are only cached while a
+/// watch on the directory can tell when they go stale, so this never returns outdated entries.
+pub fn get_or_read(dir: &Path, read: impl FnOnce(&Path) -> Vec<FileInfo>) -> Vec<FileInfo> {
+    platform::get_or_read(dir, read)
+}
+
+/// Forget the listings of directories that changed since the last call. Returns whether any did,
+/// in which case whatever is on screen is out of date.
+pub fn poll_changes() -> bool {
+    platform::poll_changes()
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::collections::HashMap;
+    use std::path::{Path, PathBuf};
+    use std::sync::{Mutex, OnceLock};
+
+    use inotify::{EventMask, Inotify, WatchDescriptor, WatchMask};
+
+    use crate::fs_utils::FileInfo;
+
+    // Every watch costs a kernel resource, so only the directories near the cursor are kept
+    const MAX_CACHED_DIRS: usize = 64;
+    const EVENT_BUFFER_SIZE: usize = 4096;
+
+    struct DirCache {
+        inotify: Inotify,
+        listings: HashMap<PathBuf, (WatchDescriptor, Vec<FileInfo>)>,
+    }
+
+    impl DirCache {
+        fn invalidate(&mut self, wd: &WatchDescriptor) {
+            self.listings.retain(|_, (watched, _)| watched != wd);
+            // Fails when the directory itself is gone, which removes the watch anyway
+            let _ = self.inotify.rm_watch(wd.clone());
+        }
+
+        fn clear(&mut self) {
+            for (_, (wd, _)) in self.listings.drain() {
+                let _ = self.inotify.rm_watch(wd);
+            }
+        }
+
+        // Drain the queued events and drop the listings they concern
+        fn process_events(&mut self) -> bool {
+            let mut buffer = [0; EVENT_BUFFER_SIZE];
+            let mut changed = false;
+
+            loop {
+                let events = match self.inotify.read_events(&mut buffer) {
+                    Ok(events) => events.map(|event| (event.wd, event.mask)).collect::<Vec<_>>(),
+                    Err(_) => {
+                        self.clear();
+                        return true;
+                    }
+                };
+                if events.is_empty() {
+                    return changed;
+                }
+
+                for (wd, mask) in events {
+                    if mask.contains(EventMask::Q_OVERFLOW) {
+                        // Events were lost, so any listing could be stale
+                        self.clear();
+                        changed = true;
+                    } else if self.listings.values().any(|(watched, _)| *watched == wd) {
+                        self.invalidate(&wd);
+                        changed = true;
+                    }
+                }
+            }
+        }
+    }
+
+    fn cache() -> Option<&'static Mutex<DirCache>> {
+        static CACHE: OnceLock<Option<Mutex<DirCache>>> = OnceLock::new();
+        CACHE
+            .get_or_init(|| {
+                Inotify::init().ok().map(|inotify| Mutex::new(DirCache { inotify, listings: HashMap::new() }))
+            })
+            .as_ref()
+    }
+
+    pub fn get_or_read(dir: &Path, read: impl FnOnce(&Path) -> Vec<FileInfo>) -> Vec<FileInfo> {
+        let Some(cache) = cache() else {
+            return read(dir);
+        };
+        let mut cache = cache.lock().unwrap();
+
+        // Changes made since the last lookup, including our own, have to be seen before a cached listing is trusted
+        cache.process_events();
+        if let Some((_, listing)) = cache.listings.get(dir) {
+            return listing.clone();
+        }
+
+        // Watching before reading means a change made in between invalidates the fresh listing rather than getting lost
+        let mask = WatchMask::CREATE | WatchMask::DELETE | WatchMask::MOVE | WatchMask::MODIFY | WatchMask::ATTRIB
+            | WatchMask::CLOSE_WRITE | WatchMask::DELETE_SELF | WatchMask::MOVE_SELF | WatchMask::ONLYDIR;
+        let Ok(wd) = cache.inotify.add_watch(dir, mask) else {
+            return read(dir);
+        };
+
+        let listing = read(dir);
+        if cache.listings.len() >= MAX_CACHED_DIRS {
+            cache.clear();
+        }
+        cache.listings.insert(dir.to_path_buf(), (wd, listing.clone()));
+        listing
+    }
+
+    pub fn poll_changes() -> bool {
+        cache().is_some_and(|cache| cache.lock().unwrap().process_events())
+    }
+}
+
+// Without inotify there is nothing to tell when a listing goes stale, so nothing is cached
+#[cfg(not(target_os = "linux"))]
+mod platform {
+    use std::path::Path;
+
+    use crate::fs_utils::FileInfo;
+
+    pub fn get_or_read(dir: &Path, read: impl FnOnce(&Path) -> Vec<FileInfo>) -> Vec<FileInfo> {
+        read(dir)
+    }
+
+    pub fn poll_changes() -> bool {
+        false
+    }
+}
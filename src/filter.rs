@@ -0,0 +1,94 @@
+use std::ffi::OsStr;
+use std::path::Path;
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+
+use crate::fs_utils::FileInfo;
+
+/// Decides which entries are listed: dotfiles, names matching the configured ignore globs and,
+/// inside a git repository, whatever its `.gitignore` files exclude.
+#[derive(Clone)]
+pub struct FileFilter {
+    pub show_hidden: bool,
+    pub respect_gitignore: bool,
+    ignore_globs: GlobSet,
+}
+
+impl Default for FileFilter {
+    fn default() -> Self {
+        FileFilter {
+            show_hidden: false,
+            respect_gitignore: false,
+            ignore_globs: GlobSet::empty(),
+        }
+    }
+}
+
+impl FileFilter {
+    /// Glob patterns are matched against entry names, e.g. `*.pyc` or `__pycache__`.
+    pub fn set_ignore_globs(&mut self, patterns: &[&str]) -> Result<(), String> {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in patterns {
+            builder.add(Glob::new(pattern).map_err(|e| e.to_string())?);
+        }
+        self.ignore_globs = builder.build().map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    /// Remove the entries of `dir` that shouldn't be listed.
+    pub fn apply(&self, dir: &Path, files: &mut Vec<FileInfo>) {
+        self.apply_keeping(dir, files, None);
+    }
+
+    /// Like `apply`, but always keeps the entry called `keep`, e.g. the hidden directory being browsed.
+    pub fn apply_keeping(&self, dir: &Path, files: &mut Vec<FileInfo>, keep: Option<&OsStr>) {
+        let gitignores = if self.respect_gitignore { load_gitignores(dir) } else { Vec::new() };
+        files.retain(|file_info| Some(file_info.name.as_os_str()) == keep || self.is_visible(dir, file_info, &gitignores));
+    }
+
+    fn is_visible(&self, dir: &Path, file_info: &FileInfo, gitignores: &[Gitignore]) -> bool {
+        if !self.show_hidden && file_info.name.as_encoded_bytes().starts_with(b".") {
+            return false;
+        }
+        if self.ignore_globs.is_match(Path::new(&file_info.name)) {
+            return false;
+        }
+
+        // The deepest .gitignore with an opinion wins, just like git
+        let path = dir.join(&file_info.name);
+        for gitignore in gitignores {
+            match gitignore.matched_path_or_any_parents(&path, file_info.is_dir) {
+                Match::Ignore(_) => return false,
+                Match::Whitelist(_) => return true,
+                Match::None => {}
+            }
+        }
+        true
+    }
+}
+
+// The .gitignore files that apply to `dir`, deepest first, ending with the repository's own exclude file.
+// Outside of a repository nothing is ignored.
+fn load_gitignores(dir: &Path) -> Vec<Gitignore> {
+    let mut gitignores = Vec::new();
+
+    for ancestor in dir.ancestors() {
+        gitignores.push(build_gitignore(ancestor, &ancestor.join(".gitignore")));
+        if ancestor.join(".git").exists() {
+            gitignores.push(build_gitignore(ancestor, &ancestor.join(".git").join("info").join("exclude")));
+            return gitignores;
+        }
+    }
+    Vec::new()
+}
+
+fn build_gitignore(root: &Path, file: &Path) -> Gitignore {
+    let mut builder = GitignoreBuilder::new(root);
+    if file.is_file() {
+        // A bad line only loses that pattern, the rest of the file still applies
+        let _ = builder.add(file);
+    }
+    builder.build().unwrap_or_else(|_| Gitignore::empty())
+}
@@ -1,19 +1,51 @@
+use std::borrow::Cow;
+#[cfg(unix)]
+use std::collections::HashMap;
+use std::env;
+use std::ffi::OsString;
 use std::fs;
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 #[cfg(unix)]
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+#[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
 use tui::widgets::ListState;
 
-use crate::preview::get_file_preview;
+use crate::preview::{get_bytes_preview, get_file_preview};
+use crate::archive;
+use crate::dir_cache;
+use crate::filter::FileFilter;
 
+// Files inside archives have to be decompressed to be previewed, so only their start is shown
+const ARCHIVE_PREVIEW_LIMIT: u64 = 1024 * 1024;
+
+#[derive(Clone, Default)]
 pub struct FileInfo {
-    pub name: String,
+    // Kept as the raw name so that entries whose names aren't valid UTF-8 can still be opened
+    pub name: OsString,
     pub perms: Option<fs::Permissions>,
     pub is_dir: bool,
     pub is_exec: bool,
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+    pub changed: Option<SystemTime>,
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    pub is_symlink: bool,
+    pub is_broken_link: bool,
+    pub symlink_target: Option<PathBuf>,
+}
+
+impl FileInfo {
+    /// The name for showing on screen, with invalid UTF-8 replaced.
+    pub fn display_name(&self) -> Cow<'_, str> {
+        self.name.to_string_lossy()
+    }
 }
 
+#[allow(clippy::ptr_arg)]
 pub fn update_selected_dir(files: &[FileInfo], current_dir: &std::path::PathBuf, selected_dir: &mut std::path::PathBuf, middle_state: &ListState, scroll_position: &mut usize) {
     if let Some(selected) = middle_state.selected() {
         if selected < files.len() {
@@ -28,11 +60,11 @@ pub fn update_selected_dir(files: &[FileInfo], current_dir: &std::path::PathBuf,
     }
 }
 
-pub fn fetch_children(selected_dir: &std::path::PathBuf, scroll_position: usize, right_pane_height: usize) -> (Vec<FileInfo>, usize) {
+pub fn fetch_children(selected_dir: &std::path::PathBuf, scroll_position: usize, right_pane_height: usize, filter: &FileFilter) -> (Vec<FileInfo>, usize) {
     if selected_dir.as_os_str().is_empty() {
-        return (vec![create_file_info("Select a directory or file".to_string())], 0);
-    } else if selected_dir.is_file() {
-        match get_file_preview(&selected_dir, scroll_position, right_pane_height) {
+        (vec![create_file_info("Select a directory or file".to_string())], 0)
+    } else if selected_dir.is_file() && !archive::is_archive(selected_dir) {
+        match get_file_preview(selected_dir, scroll_position, right_pane_height) {
             Ok((preview_text, max_scroll_position)) => {
                 (vec![create_file_info(preview_text)], max_scroll_position)
             },
@@ -40,39 +72,69 @@ pub fn fetch_children(selected_dir: &std::path::PathBuf, scroll_position: usize,
                 (vec![create_file_info("Failed to load file preview".to_string())], 0)
             }
         }
+    } else if let Some(contents) = archive::read_file(selected_dir, ARCHIVE_PREVIEW_LIMIT) {
+        match contents {
+            Ok(contents) => {
+                let (preview_text, max_scroll_position) = get_bytes_preview(&contents, scroll_position, right_pane_height);
+                (vec![create_file_info(preview_text)], max_scroll_position)
+            },
+            Err(_) => (vec![create_file_info("Failed to load file preview".to_string())], 0),
+        }
     } else {
-        let contents = get_files_and_dirs(&selected_dir);
+        let mut contents = get_files_and_dirs(selected_dir);
+        filter.apply(selected_dir, &mut contents);
         if contents.is_empty() {
-            return (vec![create_file_info("empty".to_string())], 0);
+            (vec![create_file_info("empty".to_string())], 0)
         } else {
-            return (contents, 0);
+            (contents, 0)
         }
     }
 }
 
 pub fn create_file_info(name: String) -> FileInfo {
     FileInfo {
-        name,
-        perms: None,
-        is_dir: false,
-        is_exec: false,
+        name: name.into(),
+        ..Default::default()
     }
 }
+
 pub fn get_files_and_dirs(dir: &Path) -> Vec<FileInfo> {
+    dir_cache::get_or_read(dir, read_files_and_dirs)
+}
+
+fn read_files_and_dirs(dir: &Path) -> Vec<FileInfo> {
     match fs::read_dir(dir) {
+        // Archives, and directories inside them, are listed as if they were real directories
+        Err(_) if archive::split_path(dir).is_some() => archive::list_dir(dir).unwrap_or_default(),
         Ok(entries) => entries
             .filter_map(|entry| entry.ok())
             .map(|entry| {
                 let path = entry.path();
-                let name = path.file_name().unwrap().to_str().unwrap().to_string();
-                let is_dir = path.is_dir();
-                let perms = entry.metadata().ok().map(|meta| meta.permissions());
-                let is_exec = perms.as_ref().map_or(false, |p| is_executable(p));
+                let name = entry.file_name();
+                // The entry's own metadata, which for a symlink describes the link rather than its target
+                let link_metadata = entry.metadata().ok();
+                let is_symlink = link_metadata.as_ref().is_some_and(|meta| meta.file_type().is_symlink());
+                // A link is shown with the details of what it points to, unless that doesn't exist
+                let target_metadata = if is_symlink { fs::metadata(&path).ok() } else { None };
+                let is_broken_link = is_symlink && target_metadata.is_none();
+                let metadata = target_metadata.or(link_metadata);
+                let is_dir = metadata.as_ref().is_some_and(|meta| meta.is_dir());
+                let perms = metadata.as_ref().map(|meta| meta.permissions());
+                let is_exec = perms.as_ref().is_some_and(is_executable);
+                let (uid, gid) = metadata.as_ref().map_or((None, None), get_owner_ids);
                 FileInfo {
                     name,
                     perms,
                     is_dir,
-                    is_exec
+                    is_exec,
+                    size: metadata.as_ref().map_or(0, |meta| meta.len()),
+                    modified: metadata.as_ref().and_then(|meta| meta.modified().ok()),
+                    changed: metadata.as_ref().and_then(get_change_time),
+                    uid,
+                    gid,
+                    is_symlink,
+                    is_broken_link,
+                    symlink_target: if is_symlink { fs::read_link(&path).ok() } else { None },
                 }
             })
             .collect(),
@@ -80,11 +142,104 @@ pub fn get_files_and_dirs(dir: &Path) -> Vec<FileInfo> {
     }
 }
 
+#[allow(clippy::redundant_closure)]
 pub fn get_parent_content(dir: &Path) -> Vec<FileInfo> {
     dir.parent()
         .map_or(Vec::new(), |parent| get_files_and_dirs(parent))
 }
 
+// The inode change time (ctime) on unix; Windows has no equivalent so fall back to the creation time
+#[cfg(unix)]
+fn get_change_time(metadata: &fs::Metadata) -> Option<SystemTime> {
+    use std::os::unix::fs::MetadataExt;
+    use std::time::Duration;
+
+    let seconds = u64::try_from(metadata.ctime()).ok()?;
+    let nanos = u32::try_from(metadata.ctime_nsec()).unwrap_or(0);
+    SystemTime::UNIX_EPOCH.checked_add(Duration::new(seconds, nanos))
+}
+
+#[cfg(windows)]
+fn get_change_time(metadata: &fs::Metadata) -> Option<SystemTime> {
+    metadata.created().ok()
+}
+
+#[cfg(unix)]
+fn get_owner_ids(metadata: &fs::Metadata) -> (Option<u32>, Option<u32>) {
+    use std::os::unix::fs::MetadataExt;
+    (Some(metadata.uid()), Some(metadata.gid()))
+}
+
+#[cfg(windows)]
+fn get_owner_ids(_metadata: &fs::Metadata) -> (Option<u32>, Option<u32>) {
+    (None, None)
+}
+
+/// "user:group", falling back to the numeric ids for accounts that can't be looked up.
+#[cfg(unix)]
+pub fn get_owner(file_info: &FileInfo) -> Option<String> {
+    static USERS: OnceLock<Mutex<HashMap<u32, String>>> = OnceLock::new();
+    static GROUPS: OnceLock<Mutex<HashMap<u32, String>>> = OnceLock::new();
+
+    let uid = file_info.uid?;
+    let gid = file_info.gid?;
+
+    let user = cached_name(&USERS, uid, || lookup_name(|entry: &mut libc::passwd, buffer, size, result| unsafe {
+        libc::getpwuid_r(uid, entry, buffer, size, result)
+    }, |entry| entry.pw_name));
+    let group = cached_name(&GROUPS, gid, || lookup_name(|entry: &mut libc::group, buffer, size, result| unsafe {
+        libc::getgrgid_r(gid, entry, buffer, size, result)
+    }, |entry| entry.gr_name));
+
+    Some(format!("{}:{}", user, group))
+}
+
+// The status line asks on every redraw, and the lookups can go as far as a directory server
+#[cfg(unix)]
+fn cached_name(cache: &OnceLock<Mutex<HashMap<u32, String>>>, id: u32, lookup: impl FnOnce() -> Option<String>) -> String {
+    let mut names = cache.get_or_init(Default::default).lock().unwrap();
+    names.entry(id).or_insert_with(|| lookup().unwrap_or_else(|| id.to_string())).clone()
+}
+
+#[cfg(windows)]
+pub fn get_owner(_file_info: &FileInfo) -> Option<String> {
+    None
+}
+
+// Call one of the reentrant passwd/group lookups, growing the buffer for as long as it reports ERANGE
+#[cfg(unix)]
+fn lookup_name<T>(
+    lookup: impl Fn(&mut T, *mut libc::c_char, libc::size_t, *mut *mut T) -> libc::c_int,
+    name: impl Fn(&T) -> *const libc::c_char,
+) -> Option<String> {
+    let mut buffer: Vec<libc::c_char> = vec![0; 1024];
+    loop {
+        // SAFETY: passwd and group are plain C structs for which all zeroes is a valid value
+        let mut entry: T = unsafe { std::mem::zeroed() };
+        let mut result = std::ptr::null_mut();
+        match lookup(&mut entry, buffer.as_mut_ptr(), buffer.len(), &mut result) {
+            0 if !result.is_null() => {
+                // SAFETY: on success the name points to a NUL-terminated string inside `buffer`
+                let name = unsafe { std::ffi::CStr::from_ptr(name(&entry)) };
+                return Some(name.to_string_lossy().into_owned());
+            }
+            libc::ERANGE if buffer.len() < 1 << 20 => buffer.resize(buffer.len() * 2, 0),
+            _ => return None,
+        }
+    }
+}
+
+pub fn get_octal_permissions(metadata: &fs::Permissions) -> String {
+    #[cfg(unix)]
+    {
+        format!("{:04o}", metadata.mode() & 0o7777)
+    }
+    #[cfg(windows)]
+    {
+        if metadata.readonly() { "0444".to_string() } else { "0666".to_string() }
+    }
+}
+
 pub fn get_permissions(metadata: &fs::Permissions) -> String {
     #[cfg(unix)]
     {
@@ -128,31 +283,239 @@ pub fn make_unique_path(mut path: PathBuf) -> PathBuf {
     let original_path = path.clone();
     let mut counter = 1;
 
-    while path.exists() {
+    // A broken symlink still takes up the name
+    while path.symlink_metadata().is_ok() {
+        // Built from the raw name, so one that isn't valid UTF-8 comes through unchanged
+        let mut new_name = original_path.file_stem().unwrap().to_os_string();
+        new_name.push(format!("_{}", counter));
         if let Some(extension) = original_path.extension() {
-            let new_stem = format!("{}_{}", original_path.file_stem().unwrap().to_string_lossy(), counter);
-            path.set_file_name(new_stem);
-            path.set_extension(extension);
-        } else {
-            let new_name = format!("{}_{}", original_path.file_name().unwrap().to_string_lossy(), counter);
-            path.set_file_name(&new_name);
+            new_name.push(".");
+            new_name.push(extension);
         }
+        path.set_file_name(new_name);
         counter += 1;
     }
 
     path
 }
 
+/// Receives the bytes and files copied since its previous call; returning false cancels the copy.
+pub type CopyProgress<'a> = dyn FnMut(u64, u64) -> bool + 'a;
+
 pub fn copy(src: &Path, dest: &Path) -> std::io::Result<()> {
-    if src.is_dir() {
+    if src.is_dir() && !src.is_symlink() {
         copy_dir_to(src, dest)
     } else {
         paste_file(src, dest)
     }
 }
 
+/// What to do when the destination of a copy or move already exists.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    Overwrite,
+    Skip,
+    Rename,
+    OverwriteIfNewer,
+}
+
+impl ConflictPolicy {
+    // Directories are merged rather than replaced, with the policy applied to each file inside
+    fn merges_directories(self) -> bool {
+        matches!(self, ConflictPolicy::Overwrite | ConflictPolicy::OverwriteIfNewer)
+    }
+}
+
+/// Where `src` should end up given that `dest` may already exist, or `None` if it should be skipped.
+/// Both top-level items and the entries of a merged directory are resolved through here.
+pub fn resolve_conflict(src: &Path, dest: &Path, policy: ConflictPolicy) -> Option<PathBuf> {
+    let Ok(dest_metadata) = fs::symlink_metadata(dest) else {
+        return Some(dest.to_path_buf());
+    };
+
+    match policy {
+        ConflictPolicy::Skip => None,
+        ConflictPolicy::Rename => Some(make_unique_path(dest.to_path_buf())),
+        ConflictPolicy::Overwrite => Some(dest.to_path_buf()),
+        ConflictPolicy::OverwriteIfNewer => {
+            let src_modified = fs::symlink_metadata(src).and_then(|metadata| metadata.modified()).ok()?;
+            let dest_modified = dest_metadata.modified().ok()?;
+            (src_modified > dest_modified).then(|| dest.to_path_buf())
+        }
+    }
+}
+
+fn is_merge(src_metadata: &fs::Metadata, dest: &Path, policy: ConflictPolicy) -> bool {
+    src_metadata.is_dir()
+        && policy.merges_directories()
+        && fs::symlink_metadata(dest).is_ok_and(|metadata| metadata.is_dir())
+}
+
+/// Copy `src` to `dest`, resolving an existing destination with `policy`. Returns where the copy
+/// ended up, or `None` if it was skipped.
+pub fn copy_with_progress(src: &Path, dest: &Path, policy: ConflictPolicy, progress: &mut CopyProgress) -> std::io::Result<Option<PathBuf>> {
+    let metadata = fs::symlink_metadata(src)?;
+    if is_merge(&metadata, dest, policy) {
+        check_not_into_itself(src, dest)?;
+        copy_dir_with_progress(src, dest, policy, progress)?;
+        return Ok(Some(dest.to_path_buf()));
+    }
+
+    let Some(dest) = resolve_conflict(src, dest, policy) else {
+        let (bytes, files) = get_tree_size(src);
+        report_progress(progress, bytes, files)?;
+        return Ok(None);
+    };
+    check_not_into_itself(src, &dest)?;
+    replace_with_copy(src, &metadata, &dest, policy, progress)?;
+    Ok(Some(dest))
+}
+
+// A directory copied into itself would go on copying its own copy until the disk or the stack runs out
+fn check_not_into_itself(src: &Path, dest: &Path) -> std::io::Result<()> {
+    if is_within(dest, src) {
+        Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("Cannot copy {} into itself", src.display())))
+    } else {
+        Ok(())
+    }
+}
+
+// An existing destination is only replaced once the copy is complete, and a copy that fails
+// or is cancelled is removed again, so neither a half-written item nor a lost one is left behind
+fn replace_with_copy(src: &Path, metadata: &fs::Metadata, dest: &Path, policy: ConflictPolicy, progress: &mut CopyProgress) -> std::io::Result<()> {
+    let target = if fs::symlink_metadata(dest).is_ok() {
+        hidden_sibling(dest, "part")
+    } else {
+        dest.to_path_buf()
+    };
+
+    let result = if metadata.is_symlink() {
+        copy_symlink(src, &target).and_then(|_| report_progress(progress, 0, 1))
+    } else if metadata.is_dir() {
+        copy_dir_with_progress(src, &target, policy, progress)
+    } else {
+        copy_file_with_progress(src, &target, progress)
+    };
+
+    let result = result.and_then(|_| if target != dest { rename_over(&target, dest) } else { Ok(()) });
+    if result.is_err() {
+        let _ = delete(&target);
+    }
+    result
+}
+
+// A directory can't be renamed over, so the old item is moved aside until the new one is in
+// its place, and put back if that fails
+fn rename_over(new: &Path, dest: &Path) -> std::io::Result<()> {
+    if fs::symlink_metadata(dest).is_err() {
+        return fs::rename(new, dest);
+    }
+
+    let old = hidden_sibling(dest, "old");
+    fs::rename(dest, &old)?;
+    if let Err(e) = fs::rename(new, dest) {
+        let _ = fs::rename(&old, dest);
+        return Err(e);
+    }
+    delete(&old)
+}
+
+// An unused hidden name next to `path`, on the same file system so it can be renamed into place
+fn hidden_sibling(path: &Path, suffix: &str) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    make_unique_path(path.with_file_name(format!(".{}.{}", name, suffix)))
+}
+
+/// Move `src` to `dest` with the same conflict handling as `copy_with_progress`. A rename is
+/// instant, so its progress is reported in one go; a cross-device move copies and then deletes.
+pub fn move_with_progress(src: &Path, dest: &Path, policy: ConflictPolicy, progress: &mut CopyProgress) -> std::io::Result<Option<PathBuf>> {
+    let metadata = fs::symlink_metadata(src)?;
+    if is_merge(&metadata, dest, policy) {
+        check_not_into_itself(src, dest)?;
+        for entry in fs::read_dir(src)? {
+            let entry = entry?;
+            move_with_progress(&entry.path(), &dest.join(entry.file_name()), policy, progress)?;
+        }
+        // Entries that were skipped stay behind in the source directory
+        return match fs::remove_dir(src) {
+            Err(e) if e.kind() != std::io::ErrorKind::DirectoryNotEmpty => Err(e),
+            _ => Ok(Some(dest.to_path_buf())),
+        };
+    }
+
+    let Some(dest) = resolve_conflict(src, dest, policy) else {
+        let (bytes, files) = get_tree_size(src);
+        report_progress(progress, bytes, files)?;
+        return Ok(None);
+    };
+    check_not_into_itself(src, &dest)?;
+
+    let (bytes, files) = get_tree_size(src);
+    match rename_over(src, &dest) {
+        Ok(()) => {
+            report_progress(progress, bytes, files)?;
+            return Ok(Some(dest));
+        }
+        // Moved, but the item it replaced couldn't be removed
+        Err(e) if fs::symlink_metadata(src).is_err() => return Err(e),
+        Err(_) => {}
+    }
+
+    replace_with_copy(src, &metadata, &dest, policy, progress)?;
+    delete(src)?;
+    Ok(Some(dest))
+}
+
+pub fn report_progress(progress: &mut CopyProgress, bytes: u64, files: u64) -> std::io::Result<()> {
+    if progress(bytes, files) {
+        Ok(())
+    } else {
+        Err(std::io::Error::new(std::io::ErrorKind::Interrupted, "Cancelled"))
+    }
+}
+
+pub fn is_cancelled(error: &std::io::Error) -> bool {
+    error.kind() == std::io::ErrorKind::Interrupted
+}
+
+/// Total size in bytes and number of files under `path`, without following symlinks.
+pub fn get_tree_size(path: &Path) -> (u64, u64) {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return (0, 0);
+    };
+    if !metadata.is_dir() {
+        return (metadata.len(), 1);
+    }
+
+    fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| get_tree_size(&entry.path()))
+                .fold((0, 0), |(bytes, files), (entry_bytes, entry_files)| (bytes + entry_bytes, files + entry_files))
+        })
+        .unwrap_or((0, 0))
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Delete a file or a whole directory tree. A symlink is removed itself, even when it points to a directory.
 pub fn delete(path: &Path) -> std::io::Result<()> {
-    if path.is_dir() {
+    if fs::symlink_metadata(path)?.is_dir() {
         delete_dir(path)
     } else {
         delete_file(path)
@@ -160,34 +523,262 @@ pub fn delete(path: &Path) -> std::io::Result<()> {
 }
 
 pub fn paste_file(src: &Path, dest: &Path) -> std::io::Result<()> {
-    let mut src_file = fs::File::open(src)?;
-    let mut contents = Vec::new();
-    src_file.read_to_end(&mut contents)?;
-
-    let mut dest_file = fs::File::create(dest)?;
-    dest_file.write_all(&contents)?;
-
-    Ok(())
+    if fs::symlink_metadata(src)?.is_symlink() {
+        copy_symlink(src, dest)
+    } else {
+        copy_file_with_progress(src, dest, &mut |_, _| true)
+    }
 }
 
+/// Copy the contents of `src` into `dst`, merging with it and overwriting files that already exist there.
 pub fn copy_dir_to(src: &Path, dst: &Path) -> std::io::Result<()> {
+    copy_dir_with_progress(src, dst, ConflictPolicy::Overwrite, &mut |_, _| true)
+}
+
+// Symlinks inside the tree are recreated rather than followed, so a link to a directory
+// outside of it (or to itself) never gets its target copied
+fn copy_dir_with_progress(src: &Path, dst: &Path, policy: ConflictPolicy, progress: &mut CopyProgress) -> std::io::Result<()> {
     if !dst.exists() {
         fs::create_dir(dst)?;
     }
     
     for entry_result in fs::read_dir(src)? {
         let entry = entry_result?;
-        let file_type = entry.file_type()?;
-        if file_type.is_dir() {
-            copy_dir_to(&entry.path(), &dst.join(entry.file_name()))?;
-        } else {
-            fs::copy(&entry.path(), &dst.join(entry.file_name()))?;
+        copy_with_progress(&entry.path(), &dst.join(entry.file_name()), policy, progress)?;
+    }
+
+    // Applied last so that a read-only source directory doesn't stop us from filling the copy
+    copy_metadata(&fs::metadata(src)?, dst, None)
+}
+
+/// Copy a regular file without ever holding more than one chunk of it in memory, keeping its
+/// permissions, timestamps and (where we are allowed to) its owner.
+fn copy_file_with_progress(src: &Path, dest: &Path, progress: &mut CopyProgress) -> std::io::Result<()> {
+    let mut src_file = fs::File::open(src)?;
+    let metadata = src_file.metadata()?;
+    let mut dest_file = fs::File::create(dest)?;
+
+    if !try_reflink(&src_file, &dest_file) {
+        copy_contents(&mut src_file, &mut dest_file, progress)?;
+    } else {
+        report_progress(progress, metadata.len(), 0)?;
+    }
+
+    copy_metadata(&metadata, dest, Some(&dest_file))?;
+    report_progress(progress, 0, 1)
+}
+
+const COPY_CHUNK_SIZE: usize = 1024 * 1024;
+
+fn copy_contents(src_file: &mut fs::File, dest_file: &mut fs::File, progress: &mut CopyProgress) -> std::io::Result<()> {
+    if let Some(result) = try_copy_file_range(src_file, dest_file, progress) {
+        return result;
+    }
+
+    let mut buffer = vec![0; COPY_CHUNK_SIZE];
+    loop {
+        let read = match src_file.read(&mut buffer) {
+            Ok(0) => return Ok(()),
+            Ok(read) => read,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        dest_file.write_all(&buffer[..read])?;
+        report_progress(progress, read as u64, 0)?;
+    }
+}
+
+// Share the source's extents when the filesystem supports it (btrfs, XFS, ...); nothing is read at all
+#[cfg(target_os = "linux")]
+fn try_reflink(src_file: &fs::File, dest_file: &fs::File) -> bool {
+    use std::os::unix::io::AsRawFd;
+
+    unsafe { libc::ioctl(dest_file.as_raw_fd(), libc::FICLONE, src_file.as_raw_fd()) == 0 }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn try_reflink(_src_file: &fs::File, _dest_file: &fs::File) -> bool {
+    false
+}
+
+/// Let the kernel move the data without a round trip through userspace. Returns `None` when
+/// copy_file_range can't be used for this pair of files, before anything was written.
+#[cfg(target_os = "linux")]
+fn try_copy_file_range(src_file: &fs::File, dest_file: &fs::File, progress: &mut CopyProgress) -> Option<std::io::Result<()>> {
+    use std::os::unix::io::AsRawFd;
+
+    let mut copied_any = false;
+    loop {
+        let copied = unsafe {
+            libc::copy_file_range(
+                src_file.as_raw_fd(),
+                std::ptr::null_mut(),
+                dest_file.as_raw_fd(),
+                std::ptr::null_mut(),
+                COPY_CHUNK_SIZE,
+                0,
+            )
+        };
+
+        if copied < 0 {
+            let error = std::io::Error::last_os_error();
+            let unsupported = matches!(
+                error.raw_os_error(),
+                Some(libc::ENOSYS | libc::EXDEV | libc::EINVAL | libc::EOPNOTSUPP | libc::EPERM)
+            );
+            return match error.kind() {
+                std::io::ErrorKind::Interrupted => continue,
+                _ if unsupported && !copied_any => None,
+                _ => Some(Err(error)),
+            };
+        } else if copied == 0 {
+            return Some(Ok(()));
+        }
+
+        copied_any = true;
+        if let Err(e) = report_progress(progress, copied as u64, 0) {
+            return Some(Err(e));
         }
     }
-    
+}
+
+#[cfg(not(target_os = "linux"))]
+fn try_copy_file_range(_src_file: &fs::File, _dest_file: &fs::File, _progress: &mut CopyProgress) -> Option<std::io::Result<()>> {
+    None
+}
+
+fn copy_metadata(metadata: &fs::Metadata, dest: &Path, dest_file: Option<&fs::File>) -> std::io::Result<()> {
+    let accessed = filetime::FileTime::from_last_access_time(metadata);
+    let modified = filetime::FileTime::from_last_modification_time(metadata);
+    match dest_file {
+        Some(file) => filetime::set_file_handle_times(file, Some(accessed), Some(modified))?,
+        None => filetime::set_file_times(dest, accessed, modified)?,
+    }
+
+    copy_owner(metadata, dest);
+    fs::set_permissions(dest, metadata.permissions())
+}
+
+// Only root may give files away, so failing to keep the owner is expected and not an error
+#[cfg(unix)]
+fn copy_owner(metadata: &fs::Metadata, dest: &Path) {
+    use std::os::unix::fs::MetadataExt;
+
+    if std::os::unix::fs::lchown(dest, Some(metadata.uid()), Some(metadata.gid())).is_err() {
+        let _ = std::os::unix::fs::lchown(dest, None, Some(metadata.gid()));
+    }
+}
+
+#[cfg(windows)]
+fn copy_owner(_metadata: &fs::Metadata, _dest: &Path) {}
+
+#[cfg(unix)]
+fn copy_symlink(src: &Path, dest: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(fs::read_link(src)?, dest)?;
+    if let Ok(metadata) = fs::symlink_metadata(src) {
+        copy_owner(&metadata, dest);
+        let _ = filetime::set_symlink_file_times(
+            dest,
+            filetime::FileTime::from_last_access_time(&metadata),
+            filetime::FileTime::from_last_modification_time(&metadata),
+        );
+    }
     Ok(())
 }
 
+#[cfg(windows)]
+fn copy_symlink(src: &Path, dest: &Path) -> std::io::Result<()> {
+    let target = fs::read_link(src)?;
+    if fs::metadata(src).is_ok_and(|metadata| metadata.is_dir()) {
+        std::os::windows::fs::symlink_dir(target, dest)
+    } else {
+        std::os::windows::fs::symlink_file(target, dest)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LinkKind {
+    AbsoluteSymlink,
+    RelativeSymlink,
+    HardLink,
+}
+
+/// Create `dest` as a link to `src`. Relative symlinks are worked out between the real locations
+/// of both, so they still resolve when either path goes through a symlinked directory.
+pub fn create_link(src: &Path, dest: &Path, kind: LinkKind) -> std::io::Result<()> {
+    match kind {
+        LinkKind::HardLink => fs::hard_link(src, dest),
+        LinkKind::AbsoluteSymlink => create_symlink(&std::path::absolute(src)?, dest),
+        LinkKind::RelativeSymlink => {
+            let real_parent = |path: &Path| -> std::io::Result<PathBuf> {
+                fs::canonicalize(path.parent().unwrap_or(Path::new(".")))
+            };
+            let src_name = src.file_name().unwrap_or_default();
+            let target = relative_path(&real_parent(dest)?, &real_parent(src)?.join(src_name));
+            create_symlink(&target, dest)
+        }
+    }
+}
+
+/// Whether `path` is the directory `dir` or lies somewhere inside it, comparing where they really are
+/// so that symlinked parents are seen through. `path` doesn't have to exist yet. A `dir` that is a
+/// symlink is never inside anything, since copying or moving it doesn't follow the link.
+pub fn is_within(path: &Path, dir: &Path) -> bool {
+    if !fs::symlink_metadata(dir).is_ok_and(|metadata| metadata.is_dir()) {
+        return false;
+    }
+    match (canonicalize_existing(path), fs::canonicalize(dir)) {
+        (Some(path), Ok(dir)) => path.starts_with(dir),
+        _ => false,
+    }
+}
+
+// Canonicalize the longest part of `path` that exists and append the rest as it is
+fn canonicalize_existing(path: &Path) -> Option<PathBuf> {
+    let mut missing = Vec::new();
+    let mut existing = path;
+    loop {
+        if let Ok(canonical) = fs::canonicalize(existing) {
+            return Some(missing.iter().rev().fold(canonical, |canonical, name| canonical.join(name)));
+        }
+        missing.push(existing.file_name()?);
+        existing = existing.parent()?;
+    }
+}
+
+/// The path that leads from the directory `from` to `to`, e.g. `../lib/a.so` from `/x/bin` to `/x/lib/a.so`.
+/// Both paths must be absolute.
+pub fn relative_path(from: &Path, to: &Path) -> PathBuf {
+    let from_components: Vec<_> = from.components().collect();
+    let to_components: Vec<_> = to.components().collect();
+    let common = from_components.iter().zip(&to_components).take_while(|(a, b)| a == b).count();
+
+    let mut relative = PathBuf::new();
+    for _ in common..from_components.len() {
+        relative.push("..");
+    }
+    for component in &to_components[common..] {
+        relative.push(component);
+    }
+    relative
+}
+
+#[cfg(unix)]
+fn create_symlink(target: &Path, dest: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(target, dest)
+}
+
+// Windows needs to know up front whether the link is for a directory, resolved from where the link will be
+#[cfg(windows)]
+fn create_symlink(target: &Path, dest: &Path) -> std::io::Result<()> {
+    let resolved = dest.parent().unwrap_or(Path::new(".")).join(target);
+    if fs::metadata(resolved).is_ok_and(|metadata| metadata.is_dir()) {
+        std::os::windows::fs::symlink_dir(target, dest)
+    } else {
+        std::os::windows::fs::symlink_file(target, dest)
+    }
+}
+
 pub fn delete_file(path: &Path) -> std::io::Result<()> {
     fs::remove_file(path)?;
     Ok(())
@@ -199,6 +790,7 @@ pub fn delete_dir(path: &Path) -> std::io::Result<()> {
 }
 
 // Try unix rename; only works if src & dest are on the same fs; copy+delete otherwise 
+#[allow(clippy::redundant_pattern_matching)]
 pub fn move_file(src: &Path, dest: &Path) -> std::io::Result<()> {
     if let Err(_) = fs::rename(src, dest) {
         copy(src, dest)?;
@@ -207,3 +799,71 @@ pub fn move_file(src: &Path, dest: &Path) -> std::io::Result<()> {
 
     Ok(())
 }
+
+/// `$XDG_DATA_HOME`, falling back to `~/.local/share`.
+pub fn data_home() -> std::io::Result<PathBuf> {
+    env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "Could not locate the data directory"))
+}
+
+/// URL-escape a path as the trash spec stores it, byte by byte so names that aren't valid UTF-8
+/// survive. Only unreserved characters and separators are left as is.
+pub fn encode_path(path: &Path) -> String {
+    let mut encoded = String::new();
+    for &byte in path_bytes(path).iter() {
+        if byte.is_ascii_alphanumeric() || b"-_.~/".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// Undo `encode_path`. Anything that isn't a valid escape is taken literally.
+pub fn decode_path(value: &str) -> PathBuf {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = bytes.get(index + 1..index + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[index], escaped) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                index += 1;
+            }
+        }
+    }
+    path_from_bytes(decoded)
+}
+
+#[cfg(unix)]
+fn path_bytes(path: &Path) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+    path.as_os_str().as_bytes().to_vec()
+}
+
+#[cfg(windows)]
+fn path_bytes(path: &Path) -> Vec<u8> {
+    path.to_string_lossy().into_owned().into_bytes()
+}
+
+#[cfg(unix)]
+pub fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    use std::os::unix::ffi::OsStringExt;
+    PathBuf::from(OsString::from_vec(bytes))
+}
+
+#[cfg(windows)]
+pub fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(&bytes).into_owned())
+}
@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+use tui::style::{Color, Style};
+use tui::text::{Span, Spans};
+
+const THEME_NAME: &str = "base16-ocean.dark";
+const PLAIN_TEXT: &str = "Plain Text";
+// The preview asks on every redraw, and the answer can involve reading the file
+const MAX_CACHED_SYNTAXES: usize = 16;
+
+type CachedSyntax = (Option<SystemTime>, Option<&'static SyntaxReference>);
+
+// Loading the bundled syntaxes and themes takes a noticeable moment, so it only happens on the first preview
+fn syntax_set() -> &'static SyntaxSet {
+    static SYNTAX_SET: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAX_SET.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+fn theme() -> &'static Theme {
+    static THEME: OnceLock<Theme> = OnceLock::new();
+    THEME.get_or_init(|| ThemeSet::load_defaults().themes.remove(THEME_NAME).unwrap_or_default())
+}
+
+/// The language of `path` from its extension or name (e.g. `Makefile`), or else from a shebang
+/// or mode line at the top of the file. `None` for anything that would only be plain text.
+/// The result is cached while the file is unchanged.
+pub fn detect_syntax(path: &Path) -> Option<&'static SyntaxReference> {
+    static DETECTED: OnceLock<Mutex<HashMap<PathBuf, CachedSyntax>>> = OnceLock::new();
+
+    let modified = fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+    let mut detected = DETECTED.get_or_init(Default::default).lock().unwrap();
+    if let Some((cached_modified, syntax)) = detected.get(path) {
+        if *cached_modified == modified {
+            return *syntax;
+        }
+    }
+
+    let syntax = read_syntax(path);
+    if detected.len() >= MAX_CACHED_SYNTAXES {
+        detected.clear();
+    }
+    detected.insert(path.to_path_buf(), (modified, syntax));
+    syntax
+}
+
+fn read_syntax(path: &Path) -> Option<&'static SyntaxReference> {
+    let syntax_set = syntax_set();
+    let from_name = [path.extension(), path.file_name()]
+        .into_iter()
+        .flatten()
+        .filter_map(|name| name.to_str())
+        .find_map(|name| syntax_set.find_syntax_by_extension(name));
+
+    from_name
+        .or_else(|| {
+            let mut first_line = String::new();
+            BufReader::new(File::open(path).ok()?.take(1024)).read_line(&mut first_line).ok()?;
+            syntax_set.find_syntax_by_first_line(&first_line)
+        })
+        .filter(|syntax| syntax.name != PLAIN_TEXT)
+}
+
+/// Highlight the lines of the preview window. Parsing starts at the top of the window rather than the
+/// top of the file, so a construct opened above it (say, a block comment) can be coloured wrongly,
+/// but the work stays proportional to what is on screen.
+pub fn highlight(text: &str, syntax: &SyntaxReference) -> Vec<Spans<'static>> {
+    let mut highlighter = HighlightLines::new(syntax, theme());
+
+    text.lines()
+        .map(|line| {
+            // The bundled syntaxes expect every line to end with a newline
+            let line_with_ending = format!("{}\n", line);
+            match highlighter.highlight_line(&line_with_ending, syntax_set()) {
+                Ok(ranges) => Spans::from(
+                    ranges.into_iter()
+                        .map(|(style, piece)| {
+                            let color = Color::Rgb(style.foreground.r, style.foreground.g, style.foreground.b);
+                            Span::styled(piece.trim_end_matches('\n').to_string(), Style::default().fg(color))
+                        })
+                        .collect::<Vec<_>>(),
+                ),
+                Err(_) => Spans::from(line.to_string()),
+            }
+        })
+        .collect()
+}
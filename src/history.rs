@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+
+// Enough to retrace a long session without letting the stacks grow forever
+const MAX_HISTORY_LEN: usize = 100;
+
+/// The directories visited this session, like a browser's back and forward buttons,
+/// and the entry that was selected in each one when it was left.
+#[derive(Clone, Default)]
+pub struct History {
+    back: Vec<PathBuf>,
+    forward: Vec<PathBuf>,
+    selections: HashMap<PathBuf, OsString>,
+}
+
+impl History {
+    /// Record leaving `from` for a new directory. Going somewhere new forgets the way forward.
+    pub fn visit(&mut self, from: &Path) {
+        push_capped(&mut self.back, from);
+        self.forward.clear();
+    }
+
+    /// The most recently left directory that `usable` accepts, dropping the ones it doesn't.
+    pub fn go_back(&mut self, from: &Path, usable: impl Fn(&Path) -> bool) -> Option<PathBuf> {
+        let target = pop_usable(&mut self.back, usable)?;
+        push_capped(&mut self.forward, from);
+        Some(target)
+    }
+
+    pub fn go_forward(&mut self, from: &Path, usable: impl Fn(&Path) -> bool) -> Option<PathBuf> {
+        let target = pop_usable(&mut self.forward, usable)?;
+        push_capped(&mut self.back, from);
+        Some(target)
+    }
+
+    pub fn remember_selection(&mut self, dir: &Path, name: &OsStr) {
+        self.selections.insert(dir.to_path_buf(), name.to_os_string());
+    }
+
+    /// The name of the entry that was selected when `dir` was last left.
+    pub fn selection(&self, dir: &Path) -> Option<&OsStr> {
+        self.selections.get(dir).map(OsString::as_os_str)
+    }
+}
+
+fn push_capped(stack: &mut Vec<PathBuf>, dir: &Path) {
+    if stack.last().is_some_and(|last| last == dir) {
+        return;
+    }
+    if stack.len() >= MAX_HISTORY_LEN {
+        stack.remove(0);
+    }
+    stack.push(dir.to_path_buf());
+}
+
+fn pop_usable(stack: &mut Vec<PathBuf>, usable: impl Fn(&Path) -> bool) -> Option<PathBuf> {
+    while let Some(dir) = stack.pop() {
+        if usable(&dir) {
+            return Some(dir);
+        }
+    }
+    None
+}
@@ -1,34 +1,70 @@
-use crossterm::event::{self};
+use crossterm::event::{self, Event, KeyEvent};
+use std::time::Duration;
 use tui::widgets::ListState;
 use crate::state::AppState;
 use super::fs_utils::*;
 use crate::input_handlers::modes;
+use crate::dir_cache;
+
+const TICK_RATE: Duration = Duration::from_millis(100);
+
+/// Why the main loop woke up.
+pub enum LoopEvent {
+    Key(KeyEvent),
+    // The new number of rows
+    Resize(u16),
+    // Nothing was typed, but a background job progressed or a watched directory changed
+    Tick,
+}
+
+/// Block until there is something to redraw for. Between terminal events, every tick checks
+/// whether jobs or directory watches have news, so those show up without waiting for a key.
+pub fn wait_for_event(app_state: &AppState) -> LoopEvent {
+    loop {
+        if event::poll(TICK_RATE).unwrap_or(false) {
+            match event::read() {
+                Ok(Event::Key(key_event)) => return LoopEvent::Key(key_event),
+                Ok(Event::Resize(_, rows)) => return LoopEvent::Resize(rows),
+                _ => continue,
+            }
+        }
+
+        if app_state.jobs.is_busy() || dir_cache::poll_changes() {
+            return LoopEvent::Tick;
+        }
+    }
+}
 
 pub fn handle_input(
+    key_event:               KeyEvent,
     current_dir:             &mut std::path::PathBuf,
     middle_state:            &mut ListState,
-    left_state:              &mut ListState,
     files:                   &[FileInfo],
     scroll_position:         &mut usize,
     max_scroll:              &usize,
     app_state:               &mut AppState,
 ) -> bool {
-    if let Ok(event::Event::Key(key_event)) = event::read() {
-        app_state.last_modifier = Some(key_event.modifiers);
-
-        if app_state.delete_mode {
-            return modes::handle_delete_mode(key_event.code, current_dir, middle_state, files, app_state);
-        } else if app_state.rename_mode {
-            return modes::handle_renaming_mode(key_event.code, current_dir, middle_state, files, app_state);
-        } else if app_state.search_mode {
-            return modes::handle_search_mode(key_event.code, middle_state,files, app_state);
-        } else if app_state.is_creating_file || app_state.is_creating_directory {
-            return modes::handle_creation_mode(key_event.code, current_dir, app_state);
-        } else if app_state.is_changing_permissions {
-            return modes::handle_permissions_mode(key_event.code, current_dir, middle_state, files, app_state);
-        } else {
-            return modes::handle_normal_mode(key_event.code, key_event.modifiers, current_dir, middle_state, left_state, files, scroll_position, max_scroll, app_state);
-        }
+    if app_state.pending_paste.is_some() {
+        modes::handle_conflict_mode(key_event.code, app_state)
+    } else if let Some(command) = app_state.pending_bookmark.take() {
+        modes::handle_bookmark_letter(key_event.code, command, current_dir, middle_state, files, app_state)
+    } else if app_state.bookmarks_view {
+        modes::handle_bookmarks_mode(key_event.code, current_dir, middle_state, files, app_state)
+    } else if app_state.jobs_view {
+        modes::handle_jobs_mode(key_event.code, app_state)
+    } else if app_state.trash_view {
+        modes::handle_trash_mode(key_event.code, app_state)
+    } else if app_state.delete_mode {
+        modes::handle_delete_mode(key_event.code, current_dir, middle_state, files, app_state)
+    } else if app_state.rename_mode {
+        modes::handle_renaming_mode(key_event.code, current_dir, middle_state, files, app_state)
+    } else if app_state.search_mode {
+        modes::handle_search_mode(key_event.code, middle_state,files, app_state)
+    } else if app_state.is_creating_file || app_state.is_creating_directory || app_state.is_creating_archive {
+        modes::handle_creation_mode(key_event.code, current_dir, middle_state, files, app_state)
+    } else if app_state.is_changing_permissions {
+        modes::handle_permissions_mode(key_event.code, current_dir, middle_state, files, app_state)
+    } else {
+        modes::handle_normal_mode(key_event.code, key_event.modifiers, current_dir, middle_state, files, scroll_position, max_scroll, app_state)
     }
-    false
 }
\ No newline at end of file
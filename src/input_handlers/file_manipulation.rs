@@ -1,7 +1,12 @@
+use std::path::{Path, PathBuf};
 use tui::widgets::ListState;
-use crate::fs_utils::{FileInfo, make_unique_path};
-use crate::fs_utils;
-use crate::state::AppState;
+use crate::fs_utils::{self, make_unique_path, ConflictPolicy, FileInfo, LinkKind};
+use crate::state::{AppState, PendingPaste};
+use crate::trash::Trash;
+use crate::journal::Operation;
+use crate::jobs::JobTask;
+use crate::archive;
+use super::file_navigation::adjust_selection;
 
 pub fn handle_create_file(app_state: &mut AppState) {
     app_state.is_creating_file = true;
@@ -17,20 +22,22 @@ pub fn handle_create_directory(app_state: &mut AppState) {
 
 #[cfg(target_family = "unix")]
 pub fn handle_change_permissions(
+    current_dir: &Path,
     middle_state: &ListState,
     files: &[FileInfo],
     app_state: &mut AppState,
 ) {
-    if let Some(index) = middle_state.selected() {
-        let file_name = &files[index].name;
+    let targets = get_target_files(current_dir, middle_state, files, app_state);
+    if !targets.is_empty() {
         app_state.is_changing_permissions = true;
-        app_state.prompt_message = Some(format!(" Change permissions of \"{}\": ", file_name));
+        app_state.prompt_message = Some(format!(" Change permissions of {}: ", describe_targets(&targets)));
         app_state.permissions_buffer = Some(String::new());
     }
 }
 
 #[cfg(target_family = "windows")]
 pub fn handle_change_permissions(
+    _current_dir: &Path,
     _middle_state: &ListState,
     _files: &[FileInfo],
     app_state: &mut AppState,
@@ -38,94 +45,419 @@ pub fn handle_change_permissions(
     app_state.prompt_message = Some(" Changing permissions is not supported on this platform.".to_string());
 }
 
+pub fn handle_compress(current_dir: &Path, middle_state: &ListState, files: &[FileInfo], app_state: &mut AppState) {
+    let targets = get_target_files(current_dir, middle_state, files, app_state);
+    let default_name = match targets.as_slice() {
+        [single] if single.is_dir() => single.file_name(),
+        [single] => single.file_stem(),
+        _ => current_dir.file_name(),
+    };
+
+    if !targets.is_empty() {
+        app_state.is_creating_archive = true;
+        app_state.prompt_message = Some(format!(" Compress {} into (.zip, .tar.gz, .tar.zst): ", describe_targets(&targets)));
+        app_state.creation_buffer = Some(format!("{}.tar.gz", default_name.unwrap_or_default().to_string_lossy()));
+    }
+}
+
+/// Pack the targets into `archive_name` in the background. An existing archive of that name is kept
+/// and the new one gets a numbered name instead.
+pub fn compress_files(current_dir: &Path, middle_state: &ListState, files: &[FileInfo], app_state: &mut AppState, archive_name: &str) {
+    app_state.is_creating_archive = false;
+    app_state.prompt_message = None;
+
+    if archive::ArchiveKind::from_path(Path::new(archive_name)).is_none() {
+        app_state.prompt_message = Some(format!(" Unsupported archive type: {}", archive_name));
+        return;
+    }
+
+    let targets = get_target_files(current_dir, middle_state, files, app_state);
+    if !targets.is_empty() {
+        app_state.jobs.submit(JobTask::Compress(targets, current_dir.join(archive_name)));
+        app_state.marked_files.clear();
+    }
+}
+
+/// Unpack each targeted archive into a new directory next to it, named after the archive.
+pub fn extract_archives(current_dir: &Path, middle_state: &ListState, files: &[FileInfo], app_state: &mut AppState) {
+    let (archives, others): (Vec<PathBuf>, Vec<PathBuf>) = get_target_files(current_dir, middle_state, files, app_state)
+        .into_iter()
+        .partition(|path| archive::is_archive(path));
+
+    if let Some(other) = others.first() {
+        app_state.prompt_message = Some(format!(" Not an archive: {}", other.file_name().unwrap_or_default().to_string_lossy()));
+        return;
+    }
+
+    let items: Vec<(PathBuf, PathBuf)> = archives.into_iter()
+        .filter_map(|path| {
+            let dest = path.with_file_name(archive::strip_suffix(&path)?);
+            Some((path, dest))
+        })
+        .collect();
+    if !items.is_empty() {
+        app_state.jobs.submit(JobTask::Extract(items));
+        app_state.marked_files.clear();
+    }
+}
+
+/// The marked files if there are any, otherwise the file under the cursor. Symlinks are replaced
+/// by what they point to when operations follow them; broken ones stay as they are.
+pub fn get_target_files(current_dir: &Path, middle_state: &ListState, files: &[FileInfo], app_state: &AppState) -> Vec<PathBuf> {
+    app_state.marked_files.targets(current_dir, middle_state.selected(), files, app_state.follow_symlinks)
+}
+
+pub fn toggle_follow_symlinks(app_state: &mut AppState) {
+    app_state.follow_symlinks = !app_state.follow_symlinks;
+    app_state.prompt_message = Some(if app_state.follow_symlinks {
+        " File operations follow symlinks".to_string()
+    } else {
+        " File operations act on symlinks themselves".to_string()
+    });
+}
+
+pub fn describe_targets(targets: &[PathBuf]) -> String {
+    match targets {
+        [single] => format!("\"{}\"", single.file_name().unwrap_or_default().to_string_lossy()),
+        _ => format!("{} marked items", targets.len()),
+    }
+}
+
+pub fn toggle_mark(current_dir: &Path, middle_state: &mut ListState, files: &[FileInfo], app_state: &mut AppState) {
+    if let Some(file_info) = middle_state.selected().and_then(|index| files.get(index)) {
+        app_state.marked_files.toggle(current_dir.join(&file_info.name));
+        adjust_selection(middle_state, files.len(), true);
+    }
+}
+
+pub fn mark_all(current_dir: &Path, files: &[FileInfo], app_state: &mut AppState) {
+    app_state.marked_files.mark_all(current_dir, files);
+}
+
+pub fn invert_marks(current_dir: &Path, files: &[FileInfo], app_state: &mut AppState) {
+    app_state.marked_files.invert(current_dir, files);
+}
+
+pub fn clear_marks(app_state: &mut AppState) {
+    app_state.marked_files.clear();
+}
+
+#[allow(clippy::ptr_arg)]
 pub fn copy_file(current_dir: &mut std::path::PathBuf, middle_state: &mut ListState, files: &[FileInfo], app_state: &mut AppState) {
-    if let Some(index) = middle_state.selected() {
-        if index < files.len() {
-            let potential_file = current_dir.join(&files[index].name);
-            if potential_file.exists() {
-                app_state.selected_file_for_copy = Some(potential_file);
-                app_state.was_cut = false;
+    select_files_for_copy(current_dir, middle_state, files, app_state, false);
+}
+
+#[allow(clippy::ptr_arg)]
+pub fn cut_file(current_dir: &mut std::path::PathBuf, middle_state: &mut ListState, files: &[FileInfo], app_state: &mut AppState) {
+    select_files_for_copy(current_dir, middle_state, files, app_state, true);
+}
+
+fn select_files_for_copy(current_dir: &Path, middle_state: &ListState, files: &[FileInfo], app_state: &mut AppState, was_cut: bool) {
+    let targets = get_target_files(current_dir, middle_state, files, app_state);
+    if !targets.is_empty() {
+        app_state.selected_files_for_copy = targets;
+        app_state.was_cut = was_cut;
+        app_state.marked_files.clear();
+    }
+}
+
+#[allow(clippy::ptr_arg)]
+pub fn paste_file(current_dir: &mut std::path::PathBuf, app_state: &mut AppState) {
+    let sources = std::mem::take(&mut app_state.selected_files_for_copy);
+    if !sources.is_empty() {
+        start_paste(sources, app_state.was_cut, current_dir, app_state);
+        app_state.was_cut = false;
+    }
+}
+
+/// Copy or move the selected or marked entries into the directory of the other panel, without
+/// going through the clipboard.
+pub fn transfer_to_other_panel(current_dir: &Path, middle_state: &ListState, files: &[FileInfo], app_state: &mut AppState, was_cut: bool) {
+    let Some(dest_dir) = app_state.other_panel.as_ref().map(|panel| panel.current_dir.clone()) else {
+        app_state.prompt_message = Some(" Only available in the dual-pane layout".to_string());
+        return;
+    };
+
+    let targets = get_target_files(current_dir, middle_state, files, app_state);
+    if !targets.is_empty() {
+        app_state.marked_files.clear();
+        start_paste(targets, was_cut, &dest_dir, app_state);
+    }
+}
+
+// Queue `sources` for pasting into `dest_dir`, asking about each destination that already exists first
+fn start_paste(sources: Vec<PathBuf>, was_cut: bool, dest_dir: &Path, app_state: &mut AppState) {
+    let mut pending = PendingPaste {
+        was_cut,
+        items: Vec::new(),
+        conflicts: std::collections::VecDeque::new(),
+        apply_to_all: false,
+        remembered_policy: None,
+        into_itself: Vec::new(),
+    };
+
+    for src in sources {
+        let dest = dest_dir.join(src.file_name().unwrap_or_default());
+        if fs_utils::is_within(dest_dir, &src) {
+            // A directory copied into itself would keep on copying its own copy
+            pending.into_itself.push(src);
+        } else if dest == src {
+            // Moving a file onto itself is a no-op and copying it in place always picks a fresh name
+            if !pending.was_cut {
+                pending.items.push((src, dest, ConflictPolicy::Rename));
             }
+        } else if dest.symlink_metadata().is_ok() {
+            pending.conflicts.push_back((src, dest));
+        } else {
+            pending.items.push((src, dest, ConflictPolicy::Rename));
         }
     }
+
+    app_state.pending_paste = Some(pending);
+    continue_paste(app_state);
 }
 
-pub fn cut_file(current_dir: &mut std::path::PathBuf, middle_state: &mut ListState, files: &[FileInfo], app_state: &mut AppState) {
-    if let Some(index) = middle_state.selected() {
-        if index < files.len() {
-            let potential_file = current_dir.join(&files[index].name);
-            if potential_file.exists() {
-                app_state.selected_file_for_copy = Some(potential_file);
-                app_state.was_cut = true;
+/// Link to the yanked entries from `current_dir`, under their own names or numbered ones if those are
+/// taken. Unlike a paste this leaves the clipboard alone, since the originals stay where they are.
+pub fn paste_links(current_dir: &Path, app_state: &mut AppState, kind: LinkKind) {
+    let mut undo_operations = Vec::new();
+    for src in &app_state.selected_files_for_copy {
+        let dest = make_unique_path(current_dir.join(src.file_name().unwrap_or_default()));
+        match fs_utils::create_link(src, &dest, kind) {
+            Ok(()) => undo_operations.push(Operation::Trash { path: dest }),
+            Err(e) => {
+                let file_name = src.file_name().unwrap_or_default().to_string_lossy();
+                app_state.prompt_message = Some(format!(" Failed to link to {}: {}", file_name, e));
             }
         }
     }
+    app_state.journal.record(undo_operations);
 }
 
-pub fn paste_file(current_dir: &mut std::path::PathBuf, app_state: &mut AppState) {
-    if let Some(ref src) = app_state.selected_file_for_copy {
-        let original_dest = current_dir.join(src.file_name().unwrap_or_default());
-        
-        // If the file was cut use the original dest, otherwise make it unique for copy
-        let dest = if app_state.was_cut {
-            original_dest
-        } else {
-            make_unique_path(original_dest)
-        };
-
-        if app_state.was_cut {
-            match fs_utils::move_file(src, &dest) {
-                Ok(_) => {},
-                Err(e) => {
-                    app_state.prompt_message = Some(format!(" Error while moving: {}", e));
-                }
-            }
+/// Prompt for the next conflicting destination, or start the paste once they are all resolved.
+fn continue_paste(app_state: &mut AppState) {
+    let Some(pending) = app_state.pending_paste.as_mut() else {
+        return;
+    };
+
+    while let Some((_, dest)) = pending.conflicts.front() {
+        if let Some(policy) = pending.remembered_policy {
+            resolve_next_conflict(pending, policy);
         } else {
-            match fs_utils::copy(src, &dest) {
-                Ok(_) => {},
-                Err(e) => {
-                    app_state.prompt_message = Some(format!(" Error while copying: {}", e));
-                }
-            }
+            app_state.prompt_message = Some(format!(
+                " \"{}\" already exists: (o)verwrite, (s)kip, (r)ename, overwrite if (n)ewer, (a)pply to all [{}]",
+                dest.file_name().unwrap_or_default().to_string_lossy(),
+                if pending.apply_to_all { "on" } else { "off" }
+            ));
+            return;
         }
-        app_state.selected_file_for_copy = None;
-        app_state.was_cut = false;
+    }
+
+    let pending = app_state.pending_paste.take().unwrap();
+    app_state.prompt_message = if pending.into_itself.is_empty() {
+        None
+    } else {
+        Some(format!(" Cannot paste {} into itself", describe_targets(&pending.into_itself)))
+    };
+    if pending.items.is_empty() {
+        return;
+    }
+
+    if pending.was_cut {
+        app_state.jobs.submit(JobTask::Move(pending.items));
+    } else {
+        app_state.jobs.submit(JobTask::Copy(pending.items));
+    }
+}
+
+fn resolve_next_conflict(pending: &mut PendingPaste, policy: ConflictPolicy) {
+    if let Some((src, dest)) = pending.conflicts.pop_front() {
+        if policy != ConflictPolicy::Skip {
+            pending.items.push((src, dest, policy));
+        }
+    }
+}
+
+pub fn resolve_paste_conflict(app_state: &mut AppState, policy: ConflictPolicy) {
+    if let Some(pending) = app_state.pending_paste.as_mut() {
+        if pending.apply_to_all {
+            pending.remembered_policy = Some(policy);
+        }
+        resolve_next_conflict(pending, policy);
+    }
+    continue_paste(app_state);
+}
+
+pub fn toggle_apply_to_all(app_state: &mut AppState) {
+    if let Some(pending) = app_state.pending_paste.as_mut() {
+        pending.apply_to_all = !pending.apply_to_all;
+    }
+    continue_paste(app_state);
+}
+
+/// Abort the whole paste and put the files back on the clipboard so it can be retried.
+pub fn cancel_paste(app_state: &mut AppState) {
+    if let Some(pending) = app_state.pending_paste.take() {
+        app_state.selected_files_for_copy = pending.items.into_iter()
+            .map(|(src, _, _)| src)
+            .chain(pending.conflicts.into_iter().map(|(src, _)| src))
+            .collect();
+        app_state.was_cut = pending.was_cut;
+    }
+    app_state.prompt_message = None;
+}
+
+/// Record what finished background jobs did so it can be undone, and report their failures.
+pub fn collect_finished_jobs(app_state: &mut AppState) {
+    for outcome in app_state.jobs.poll() {
+        app_state.journal.record(outcome.undo_operations);
+
+        if outcome.cancelled {
+            app_state.prompt_message = Some(format!(" Cancelled: {}", outcome.description));
+        } else if let Some(error) = outcome.error {
+            app_state.prompt_message = Some(format!(" {} failed: {}", outcome.description, error));
+        }
+    }
+
+    let jobs_len = app_state.jobs.jobs().len();
+    if app_state.jobs_state.selected().is_some_and(|index| index >= jobs_len) {
+        app_state.jobs_state.select(jobs_len.checked_sub(1));
+    }
+}
+
+pub fn open_jobs_view(app_state: &mut AppState) {
+    app_state.jobs_view = true;
+    app_state.jobs_state.select(if app_state.jobs.is_busy() { Some(0) } else { None });
+}
+
+pub fn close_jobs_view(app_state: &mut AppState) {
+    app_state.jobs_view = false;
+}
+
+pub fn cancel_job(app_state: &mut AppState) {
+    let selected = app_state.jobs_state.selected().and_then(|index| app_state.jobs.jobs().get(index));
+    if let Some(id) = selected.map(|job| job.id) {
+        app_state.jobs.cancel(id);
+        app_state.prompt_message = Some(" Cancelling job...".to_string());
     }
 }
 
-pub fn handle_delete(middle_state: &mut ListState, files: &[FileInfo], app_state: &mut AppState) {
+pub fn handle_delete(current_dir: &Path, middle_state: &mut ListState, files: &[FileInfo], app_state: &mut AppState, permanent: bool) {
     if app_state.prompt_message.is_none() {
-        if let Some(index) = middle_state.selected() {
-            if index < files.len() {
-                let file_name = &files[index].name;
-                app_state.prompt_message = Some(format!(" Are you sure you want to delete {}? (y/n)", file_name));
-                app_state.delete_mode = true;
-            }
+        let targets = get_target_files(current_dir, middle_state, files, app_state);
+        if !targets.is_empty() {
+            app_state.prompt_message = Some(if permanent {
+                format!(" Are you sure you want to permanently delete {}? (y/n)", describe_targets(&targets))
+            } else {
+                format!(" Move {} to the trash? (y/n)", describe_targets(&targets))
+            });
+            app_state.delete_mode = true;
+            app_state.permanent_delete = permanent;
         }
     }
 }
 
+#[allow(clippy::ptr_arg)]
 pub fn delete_file(current_dir: &mut std::path::PathBuf, middle_state: &mut ListState, files: &[FileInfo], app_state: &mut AppState) {
-    if let Some(index) = middle_state.selected() {
-        if index < files.len() {
-            let potential_file = current_dir.join(&files[index].name);
-            match fs_utils::delete(&potential_file) {
-                Ok(_) => {},
-                Err(e) => {
-                    app_state.prompt_message = Some(format!(" Error while deleting: {}", e));
-                }
+    let targets = get_target_files(current_dir, middle_state, files, app_state);
+
+    app_state.jobs.submit(if app_state.permanent_delete {
+        JobTask::Delete(targets)
+    } else {
+        JobTask::Trash(targets)
+    });
+    app_state.marked_files.clear();
+}
+
+pub fn open_trash_view(app_state: &mut AppState) {
+    app_state.trash_view = true;
+    refresh_trash_entries(app_state);
+}
+
+pub fn close_trash_view(app_state: &mut AppState) {
+    app_state.trash_view = false;
+    app_state.is_emptying_trash = false;
+    app_state.trash_entries.clear();
+}
+
+fn refresh_trash_entries(app_state: &mut AppState) {
+    let entries = Trash::all().and_then(|trashes| {
+        trashes.iter().map(Trash::list).collect::<std::io::Result<Vec<_>>>()
+    });
+    match entries {
+        Ok(entries) => {
+            app_state.trash_entries = entries.into_iter().flatten().collect();
+            app_state.trash_entries.sort_by(|a, b| b.deletion_date.cmp(&a.deletion_date));
+        }
+        Err(e) => {
+            app_state.trash_entries.clear();
+            app_state.prompt_message = Some(format!(" Failed to read the trash: {}", e));
+        }
+    }
+
+    let selected = app_state.trash_state.selected().unwrap_or(0);
+    if app_state.trash_entries.is_empty() {
+        app_state.trash_state.select(None);
+    } else {
+        app_state.trash_state.select(Some(selected.min(app_state.trash_entries.len() - 1)));
+    }
+}
+
+pub fn restore_from_trash(app_state: &mut AppState) {
+    if let Some(entry) = app_state.trash_state.selected().and_then(|index| app_state.trash_entries.get(index)) {
+        match Trash::new(&entry.trash_dir).restore(entry) {
+            Ok(destination) => {
+                app_state.prompt_message = Some(format!(" Restored {}", destination.display()));
+                app_state.journal.record(vec![Operation::Trash { path: destination }]);
+            }
+            Err(e) => {
+                app_state.prompt_message = Some(format!(" Failed to restore {}: {}", entry.original_path.display(), e));
             }
         }
+        refresh_trash_entries(app_state);
     }
 }
 
+pub fn handle_empty_trash(app_state: &mut AppState) {
+    if !app_state.trash_entries.is_empty() {
+        app_state.is_emptying_trash = true;
+        app_state.prompt_message = Some(format!(" Permanently delete all {} items in the trash? (y/n)", app_state.trash_entries.len()));
+    }
+}
+
+pub fn empty_trash(app_state: &mut AppState) {
+    app_state.is_emptying_trash = false;
+    let result = Trash::all().and_then(|trashes| trashes.iter().try_for_each(Trash::empty));
+    app_state.prompt_message = match result {
+        Ok(_) => None,
+        Err(e) => Some(format!(" Failed to empty the trash: {}", e)),
+    };
+    refresh_trash_entries(app_state);
+}
+
+pub fn undo(app_state: &mut AppState) {
+    app_state.prompt_message = match app_state.journal.undo() {
+        Some(Ok(description)) => Some(format!(" Undone: {}", description)),
+        Some(Err(e)) => Some(format!(" Undo failed: {}", e)),
+        None => Some(" Nothing to undo".to_string()),
+    };
+}
+
+pub fn redo(app_state: &mut AppState) {
+    app_state.prompt_message = match app_state.journal.redo() {
+        Some(Ok(description)) => Some(format!(" Redone: {}", description)),
+        Some(Err(e)) => Some(format!(" Redo failed: {}", e)),
+        None => Some(" Nothing to redo".to_string()),
+    };
+}
+
 pub fn handle_rename(
     middle_state: &ListState,
     files: &[FileInfo],
     app_state: &mut AppState,
 ) {
     if let Some(index) = middle_state.selected() {
-        let file_name = &files[index].name;
+        let file_name = files[index].display_name();
         app_state.rename_mode = true;
         app_state.prompt_message = Some(format!(" Rename \"{}\" to: ", file_name));
         app_state.renaming_buffer = Some(String::new());
@@ -1,14 +1,16 @@
-use crossterm::event::KeyModifiers;
 use tui::widgets::ListState;
 use crate::fs_utils::FileInfo;
-use crate::state::AppState;
+use crate::state::{AppState, BookmarkCommand};
+use crate::tabs::Tab;
+use crate::history::History;
+use crate::archive;
 use std::process::{Command, Stdio};
 use std::env;
-
-const GO_TO_TOP: char = 'g';
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
 
 pub fn search_files(pattern: &str, files: &[FileInfo], start_index: usize, reverse: bool) -> Option<usize> {
-    let regex_match = |index: usize| regex::Regex::new(pattern).ok().map_or(false, |re| re.is_match(&files[index].name));
+    let regex_match = |index: usize| regex::Regex::new(pattern).ok().is_some_and(|re| re.is_match(&files[index].display_name()));
 
     if reverse {
         // Start from the file just before the start_index
@@ -28,10 +30,11 @@ pub fn search_files(pattern: &str, files: &[FileInfo], start_index: usize, rever
 pub fn move_in(current_dir: &mut std::path::PathBuf, middle_state: &mut ListState, files: &[FileInfo], app_state: &mut AppState) {
     if let Some(index) = middle_state.selected() {
         let potential_path = current_dir.join(&files[index].name);
-        if potential_path.is_dir() {
-            *current_dir = potential_path;
-            middle_state.select(Some(0));
+        if is_browsable(&potential_path) {
+            visit_dir(current_dir, potential_path, middle_state, files, app_state);
 
+        } else if archive::split_path(&potential_path).is_some() {
+            app_state.prompt_message = Some(" Files inside archives can only be previewed.".to_string());
         } else if potential_path.is_file() {
             let editor = get_editor();
             
@@ -42,11 +45,12 @@ pub fn move_in(current_dir: &mut std::path::PathBuf, middle_state: &mut ListStat
                     .status()
             } else if cfg!(windows) {
                 Command::new("cmd")
-                    .args(["/C", &editor, potential_path.to_str().unwrap()])
+                    .args(["/C", &editor])
+                    .arg(potential_path.as_os_str())
                     .stderr(Stdio::null())
                     .status()
             } else {
-                Err(std::io::Error::new(std::io::ErrorKind::Other, "Unsupported platform."))
+                Err(std::io::Error::other("Unsupported platform."))
             };
             
             match result {
@@ -82,72 +86,93 @@ pub fn get_editor() -> String {
     }
 }
 
-pub fn move_out(current_dir: &mut std::path::PathBuf, middle_state: &mut ListState, left_state: &mut ListState) {
-    if let Some(parent) = current_dir.parent() {
-        *current_dir = parent.to_path_buf();
-        middle_state.select(Some(0));
-    } else {
-        left_state.select(None);
+/// Go to the parent directory with the directory we came from selected.
+pub fn move_out(current_dir: &mut PathBuf, middle_state: &mut ListState, files: &[FileInfo], app_state: &mut AppState) {
+    if let (Some(parent), Some(child)) = (current_dir.parent(), current_dir.file_name()) {
+        let (parent, child) = (parent.to_path_buf(), child.to_os_string());
+        visit_dir(current_dir, parent, middle_state, files, app_state);
+        app_state.select_on_refresh = Some(child);
     }
 }
 
-pub fn move_down(middle_state: &mut ListState, max_len: usize,scroll_position: &mut usize,max_scroll: &usize, app_state: &mut AppState) {
-    if app_state.last_modifier == Some(KeyModifiers::ALT) {
-        if *scroll_position < *max_scroll {
-            *scroll_position += 1;
-        }
-    } else if app_state.last_modifier == Some(KeyModifiers::NONE) {
-        adjust_selection(middle_state, max_len, true);
+pub fn go_back(current_dir: &mut PathBuf, middle_state: &mut ListState, files: &[FileInfo], app_state: &mut AppState) {
+    match app_state.history.go_back(current_dir, is_browsable) {
+        Some(dir) => enter_dir(current_dir, dir, middle_state, files, app_state),
+        None => app_state.prompt_message = Some(" Already at the oldest directory".to_string()),
     }
 }
 
-pub fn move_up(middle_state: &mut ListState, max_len: usize,scroll_position: &mut usize, app_state: &mut AppState) {
-    if app_state.last_modifier == Some(KeyModifiers::ALT) {
-        if *scroll_position > 0 {
-            *scroll_position -= 1;
-        }
-    } else if app_state.last_modifier == Some(KeyModifiers::NONE) {
-        adjust_selection(middle_state, max_len, false);
+pub fn go_forward(current_dir: &mut PathBuf, middle_state: &mut ListState, files: &[FileInfo], app_state: &mut AppState) {
+    match app_state.history.go_forward(current_dir, is_browsable) {
+        Some(dir) => enter_dir(current_dir, dir, middle_state, files, app_state),
+        None => app_state.prompt_message = Some(" Already at the newest directory".to_string()),
     }
 }
 
-pub fn move_down_half(middle_state: &mut ListState, files_len: usize, scroll_position: &mut usize,max_scroll: &usize, app_state: &mut AppState) {
-    let half_screen = app_state.terminal_height / 2;
+// Whether `path` can be listed like a directory, which includes archives and directories inside them
+fn is_browsable(path: &Path) -> bool {
+    path.is_dir() || archive::is_archive(path) || archive::list_dir(path).is_some()
+}
 
-    if app_state.last_modifier == Some(KeyModifiers::CONTROL) {
-        app_state.last_modifier = Some(KeyModifiers::NONE);
+// Go to `dir` as a new step in the history
+fn visit_dir(current_dir: &mut PathBuf, dir: PathBuf, middle_state: &mut ListState, files: &[FileInfo], app_state: &mut AppState) {
+    if dir != *current_dir {
+        app_state.history.visit(current_dir);
+        enter_dir(current_dir, dir, middle_state, files, app_state);
+    }
+}
 
-        for _ in 0..half_screen{
-            move_down(middle_state, files_len, scroll_position, max_scroll, app_state); 
-        }
-    } else if app_state.last_modifier == Some(KeyModifiers::ALT) {
-        let new_position = *scroll_position + half_screen; 
-        if new_position <= *max_scroll {
-            *scroll_position = new_position;
-        } else {
-            *scroll_position = *max_scroll;
-        }
+// Switch to `dir`, remembering the selection in the directory being left and restoring the one in `dir`
+fn enter_dir(current_dir: &mut PathBuf, dir: PathBuf, middle_state: &mut ListState, files: &[FileInfo], app_state: &mut AppState) {
+    if let Some(file_info) = middle_state.selected().and_then(|index| files.get(index)) {
+        app_state.history.remember_selection(current_dir, &file_info.name);
     }
+
+    app_state.select_on_refresh = app_state.history.selection(&dir).map(OsStr::to_os_string);
+    *current_dir = dir;
+    middle_state.select(Some(0));
 }
 
-pub fn move_up_half(middle_state: &mut ListState, files_len: usize, scroll_position: &mut usize, app_state: &mut AppState) {
-    let half_screen = app_state.terminal_height / 2;
+pub fn move_down(middle_state: &mut ListState, max_len: usize) {
+    adjust_selection(middle_state, max_len, true);
+}
 
-    if app_state.last_modifier == Some(KeyModifiers::CONTROL) {
-        app_state.last_modifier = Some(KeyModifiers::NONE);
- 
-        for _ in 0..half_screen {
-            move_up(middle_state, files_len,scroll_position, app_state); 
-        }
-    } else if app_state.last_modifier == Some(KeyModifiers::ALT) {
-        if *scroll_position >= half_screen{
-            *scroll_position -= half_screen;
-        } else {
-            *scroll_position = 0;
-        }
+pub fn move_up(middle_state: &mut ListState, max_len: usize) {
+    adjust_selection(middle_state, max_len, false);
+}
+
+pub fn move_down_half(middle_state: &mut ListState, files_len: usize, app_state: &AppState) {
+    for _ in 0..app_state.terminal_height / 2 {
+        move_down(middle_state, files_len);
+    }
+}
+
+pub fn move_up_half(middle_state: &mut ListState, files_len: usize, app_state: &AppState) {
+    for _ in 0..app_state.terminal_height / 2 {
+        move_up(middle_state, files_len);
+    }
+}
+
+pub fn scroll_preview_down(scroll_position: &mut usize, max_scroll: &usize) {
+    if *scroll_position < *max_scroll {
+        *scroll_position += 1;
+    }
+}
+
+pub fn scroll_preview_up(scroll_position: &mut usize) {
+    if *scroll_position > 0 {
+        *scroll_position -= 1;
     }
 }
 
+pub fn scroll_preview_down_half(scroll_position: &mut usize, max_scroll: &usize, app_state: &AppState) {
+    *scroll_position = (*scroll_position + app_state.terminal_height / 2).min(*max_scroll);
+}
+
+pub fn scroll_preview_up_half(scroll_position: &mut usize, app_state: &AppState) {
+    *scroll_position = scroll_position.saturating_sub(app_state.terminal_height / 2);
+}
+
 pub fn handle_search(app_state: &mut AppState) {
     app_state.search_mode = true;
     app_state.search_pattern = None;
@@ -182,29 +207,237 @@ pub fn previous_search(
     }
 }
 
-pub fn go_to_top(middle_state: &mut ListState, app_state: &mut AppState,scroll_position: &mut usize) {
-    if app_state.last_key_pressed == Some(GO_TO_TOP) {
-        if app_state.last_modifier == Some(KeyModifiers::NONE) {
-            middle_state.select(Some(0));
-        } else {
-            *scroll_position = 0;
+pub fn cycle_sort_mode(middle_state: &ListState, files: &[FileInfo], app_state: &mut AppState) {
+    app_state.sort_options.mode = app_state.sort_options.mode.next();
+    keep_selection_on_refresh(middle_state, files, app_state);
+    app_state.prompt_message = Some(app_state.sort_options.describe());
+}
+
+pub fn toggle_sort_reverse(middle_state: &ListState, files: &[FileInfo], app_state: &mut AppState) {
+    app_state.sort_options.reverse = !app_state.sort_options.reverse;
+    keep_selection_on_refresh(middle_state, files, app_state);
+    app_state.prompt_message = Some(app_state.sort_options.describe());
+}
+
+pub fn toggle_dirs_first(middle_state: &ListState, files: &[FileInfo], app_state: &mut AppState) {
+    app_state.sort_options.dirs_first = !app_state.sort_options.dirs_first;
+    keep_selection_on_refresh(middle_state, files, app_state);
+    app_state.prompt_message = Some(app_state.sort_options.describe());
+}
+
+pub fn toggle_hidden(middle_state: &ListState, files: &[FileInfo], app_state: &mut AppState) {
+    app_state.file_filter.show_hidden = !app_state.file_filter.show_hidden;
+    keep_selection_on_refresh(middle_state, files, app_state);
+    let state = if app_state.file_filter.show_hidden { "shown" } else { "hidden" };
+    app_state.prompt_message = Some(format!(" Hidden files {}", state));
+}
+
+pub fn toggle_gitignore(middle_state: &ListState, files: &[FileInfo], app_state: &mut AppState) {
+    app_state.file_filter.respect_gitignore = !app_state.file_filter.respect_gitignore;
+    keep_selection_on_refresh(middle_state, files, app_state);
+    let state = if app_state.file_filter.respect_gitignore { "hidden" } else { "shown" };
+    app_state.prompt_message = Some(format!(" Files ignored by git {}", state));
+}
+
+// The listing is rebuilt on the next refresh, so remember which file to select once it is
+fn keep_selection_on_refresh(middle_state: &ListState, files: &[FileInfo], app_state: &mut AppState) {
+    if let Some(file_info) = middle_state.selected().and_then(|index| files.get(index)) {
+        app_state.select_on_refresh = Some(file_info.name.clone());
+    }
+}
+
+pub fn start_bookmark_command(app_state: &mut AppState, command: BookmarkCommand) {
+    app_state.pending_bookmark = Some(command);
+    app_state.prompt_message = Some(match command {
+        BookmarkCommand::Set => " Bookmark this directory as: ".to_string(),
+        BookmarkCommand::Jump => " Jump to bookmark: ".to_string(),
+    });
+}
+
+pub fn set_bookmark(current_dir: &Path, app_state: &mut AppState, letter: char) {
+    app_state.prompt_message = Some(match app_state.bookmarks.set(letter, current_dir) {
+        Ok(()) => format!(" Bookmarked {} as {}", current_dir.display(), letter),
+        Err(e) => format!(" Failed to save the bookmarks: {}", e),
+    });
+}
+
+pub fn jump_to_bookmark(current_dir: &mut PathBuf, middle_state: &mut ListState, files: &[FileInfo], app_state: &mut AppState, letter: char) {
+    match app_state.bookmarks.get(letter) {
+        Some(dir) if dir.is_dir() => {
+            let dir = dir.to_path_buf();
+            visit_dir(current_dir, dir, middle_state, files, app_state);
         }
-        app_state.last_key_pressed = None;
-    } else {
-        app_state.last_key_pressed = Some(GO_TO_TOP);
+        Some(dir) => app_state.prompt_message = Some(format!(" Bookmarked directory no longer exists: {}", dir.display())),
+        None => app_state.prompt_message = Some(format!(" No bookmark named {}", letter)),
+    }
+}
+
+pub fn open_bookmarks_view(app_state: &mut AppState) {
+    app_state.bookmarks_view = true;
+    clamp_bookmarks_selection(app_state);
+}
+
+pub fn close_bookmarks_view(app_state: &mut AppState) {
+    app_state.bookmarks_view = false;
+}
+
+pub fn jump_to_selected_bookmark(current_dir: &mut PathBuf, middle_state: &mut ListState, files: &[FileInfo], app_state: &mut AppState) {
+    let selected = app_state.bookmarks_state.selected().and_then(|index| app_state.bookmarks.iter().nth(index));
+    if let Some((letter, _)) = selected {
+        jump_to_bookmark(current_dir, middle_state, files, app_state, letter);
+        close_bookmarks_view(app_state);
     }
 }
 
-pub fn go_to_bottom(middle_state: &mut ListState, app_state: &mut AppState, files_len: usize, scroll_position: &mut usize, max_scroll: &usize) {
-    if app_state.last_modifier == Some(KeyModifiers::SHIFT) {
-        if files_len > 0 {
-            middle_state.select(Some(files_len - 1));
+pub fn delete_selected_bookmark(app_state: &mut AppState) {
+    let selected = app_state.bookmarks_state.selected().and_then(|index| app_state.bookmarks.iter().nth(index));
+    if let Some((letter, _)) = selected {
+        if let Err(e) = app_state.bookmarks.remove(letter) {
+            app_state.prompt_message = Some(format!(" Failed to save the bookmarks: {}", e));
         }
+        clamp_bookmarks_selection(app_state);
+    }
+}
+
+fn clamp_bookmarks_selection(app_state: &mut AppState) {
+    let selected = app_state.bookmarks_state.selected().unwrap_or(0);
+    if app_state.bookmarks.is_empty() {
+        app_state.bookmarks_state.select(None);
     } else {
-        *scroll_position = *max_scroll;
+        app_state.bookmarks_state.select(Some(selected.min(app_state.bookmarks.len() - 1)));
+    }
+}
+
+/// Open a tab in the home directory, with the configured sorting and filtering, right after this one.
+pub fn new_tab(current_dir: &mut PathBuf, middle_state: &mut ListState, scroll_position: &mut usize, app_state: &mut AppState) {
+    let home = env::var_os("HOME").or_else(|| env::var_os("USERPROFILE")).map(PathBuf::from);
+    let mut middle = ListState::default();
+    middle.select(Some(0));
+    let tab = Tab {
+        current_dir: home.filter(|dir| dir.is_dir()).unwrap_or_else(|| current_dir.clone()),
+        middle_state: middle,
+        scroll_position: 0,
+        sort_options: app_state.config.sort_options,
+        file_filter: app_state.config.filter.clone(),
+        history: History::default(),
+        other_panel: None,
+        left_panel_active: true,
+    };
+    open_tab(tab, current_dir, middle_state, scroll_position, app_state);
+}
+
+/// Open a copy of this tab right after it.
+pub fn duplicate_tab(current_dir: &mut PathBuf, middle_state: &mut ListState, scroll_position: &mut usize, app_state: &mut AppState) {
+    let tab = stash_tab(current_dir, middle_state, scroll_position, app_state);
+    open_tab(tab, current_dir, middle_state, scroll_position, app_state);
+}
+
+pub fn close_tab(current_dir: &mut PathBuf, middle_state: &mut ListState, scroll_position: &mut usize, app_state: &mut AppState) {
+    match app_state.tabs.close() {
+        Some(tab) => show_tab(tab, current_dir, middle_state, scroll_position, app_state),
+        None => app_state.prompt_message = Some(" Cannot close the last tab".to_string()),
+    }
+}
+
+pub fn next_tab(current_dir: &mut PathBuf, middle_state: &mut ListState, scroll_position: &mut usize, app_state: &mut AppState) {
+    switch_to_tab(app_state.tabs.next(), current_dir, middle_state, scroll_position, app_state);
+}
+
+pub fn previous_tab(current_dir: &mut PathBuf, middle_state: &mut ListState, scroll_position: &mut usize, app_state: &mut AppState) {
+    switch_to_tab(app_state.tabs.previous(), current_dir, middle_state, scroll_position, app_state);
+}
+
+/// Switch between the Miller columns and two independent panels side by side. The second panel
+/// starts out as a copy of the first.
+pub fn toggle_dual_pane(current_dir: &Path, middle_state: &ListState, scroll_position: &usize, app_state: &mut AppState) {
+    if app_state.other_panel.take().is_none() {
+        app_state.other_panel = Some(stash_panel(current_dir, middle_state, scroll_position, app_state));
+        app_state.left_panel_active = true;
+    }
+}
+
+pub fn switch_panel(current_dir: &mut PathBuf, middle_state: &mut ListState, scroll_position: &mut usize, app_state: &mut AppState) {
+    if let Some(other_panel) = app_state.other_panel.take() {
+        let previous = stash_panel(current_dir, middle_state, scroll_position, app_state);
+        show_panel(other_panel, current_dir, middle_state, scroll_position, app_state);
+        app_state.other_panel = Some(previous);
+        app_state.left_panel_active = !app_state.left_panel_active;
     }
 }
 
+fn open_tab(tab: Tab, current_dir: &mut PathBuf, middle_state: &mut ListState, scroll_position: &mut usize, app_state: &mut AppState) {
+    let previous = stash_tab(current_dir, middle_state, scroll_position, app_state);
+    app_state.tabs.open(previous);
+    show_tab(tab, current_dir, middle_state, scroll_position, app_state);
+}
+
+// Put the tab on screen into the background and bring up the one at `index`, counting all tabs in order
+fn switch_to_tab(index: usize, current_dir: &mut PathBuf, middle_state: &mut ListState, scroll_position: &mut usize, app_state: &mut AppState) {
+    if index == app_state.tabs.active() {
+        return;
+    }
+    let previous = stash_tab(current_dir, middle_state, scroll_position, app_state);
+    let tab = app_state.tabs.switch_to(index, previous);
+    show_tab(tab, current_dir, middle_state, scroll_position, app_state);
+}
+
+// The tab on screen along with its dual-pane layout, which comes back when the tab does
+fn stash_tab(current_dir: &Path, middle_state: &ListState, scroll_position: &usize, app_state: &AppState) -> Tab {
+    Tab {
+        other_panel: app_state.other_panel.clone().map(Box::new),
+        left_panel_active: app_state.left_panel_active,
+        ..stash_panel(current_dir, middle_state, scroll_position, app_state)
+    }
+}
+
+fn show_tab(mut tab: Tab, current_dir: &mut PathBuf, middle_state: &mut ListState, scroll_position: &mut usize, app_state: &mut AppState) {
+    app_state.other_panel = tab.other_panel.take().map(|panel| *panel);
+    app_state.left_panel_active = tab.left_panel_active;
+    show_panel(tab, current_dir, middle_state, scroll_position, app_state);
+}
+
+// Just the panel on screen, without the rest of the tab's layout
+fn stash_panel(current_dir: &Path, middle_state: &ListState, scroll_position: &usize, app_state: &AppState) -> Tab {
+    Tab {
+        current_dir: current_dir.to_path_buf(),
+        middle_state: middle_state.clone(),
+        scroll_position: *scroll_position,
+        sort_options: app_state.sort_options,
+        file_filter: app_state.file_filter.clone(),
+        history: app_state.history.clone(),
+        other_panel: None,
+        left_panel_active: true,
+    }
+}
+
+fn show_panel(tab: Tab, current_dir: &mut PathBuf, middle_state: &mut ListState, scroll_position: &mut usize, app_state: &mut AppState) {
+    *current_dir = tab.current_dir;
+    *middle_state = tab.middle_state;
+    *scroll_position = tab.scroll_position;
+    app_state.sort_options = tab.sort_options;
+    app_state.file_filter = tab.file_filter;
+    app_state.history = tab.history;
+    app_state.select_on_refresh = None;
+}
+
+pub fn go_to_top(middle_state: &mut ListState) {
+    middle_state.select(Some(0));
+}
+
+pub fn go_to_bottom(middle_state: &mut ListState, files_len: usize) {
+    if files_len > 0 {
+        middle_state.select(Some(files_len - 1));
+    }
+}
+
+pub fn scroll_preview_to_top(scroll_position: &mut usize) {
+    *scroll_position = 0;
+}
+
+pub fn scroll_preview_to_bottom(scroll_position: &mut usize, max_scroll: &usize) {
+    *scroll_position = *max_scroll;
+}
+
 pub fn handle_quit() -> bool {
     true
 }
@@ -1,39 +1,38 @@
 use crossterm::event::{KeyCode, KeyModifiers};
 use tui::widgets::ListState;
-use crate::fs_utils::FileInfo;
-use crate::state::AppState;
+use crate::fs_utils::{ConflictPolicy, FileInfo, LinkKind};
+use crate::state::{AppState, BookmarkCommand};
+use crate::config::{Action, KeyLookup, KeyPress};
+use crate::journal::Operation;
+use crate::bookmarks::is_bookmark_letter;
 use super::file_manipulation::*;
 use super::file_navigation::*;
 
+// Fixed keys of the views and prompts; only normal mode goes through the keymap
 const MOVE_DOWN:             char = 'j';
 const MOVE_UP:               char = 'k';
-const MOVE_IN:               char = 'l';
-const MOVE_OUT:              char = 'h';
 const QUIT:                  char = 'q';
-const GO_TO_TOP:             char = 'g';
-const GO_TO_BOTTOM:          char = 'G';
-const COPY:                  char = 'y';
-const PASTE:                 char = 'p';
-const DELETE:                char = 'D';
-const CUT:                   char = 'x';
-const MOVE_UP_HALF_PAGE:     char = 'u';
-const MOVE_DOWN_HALF_PAGE:   char = 'd';
 const YES:                   char = 'y';
 const NO:                    char = 'n';
-const RENAME:                char = 'r';
-const SEARCH:                char = '/';
-const NEXT:                  char = 'n';
-const PREVIOUS:              char = 'N';
-const CREATE_FILE:           char = 'a';
-const CREATE_DIR:            char = 'A';
-const CHANGE_PERMISSIONS:    char = 'c';
+const TRASH_VIEW:            char = 'T';
+const RESTORE:               char = 'r';
+const EMPTY_TRASH:           char = 'E';
+const JOBS_VIEW:             char = 'J';
+const CANCEL_JOB:            char = 'c';
+const OVERWRITE:             char = 'o';
+const SKIP:                  char = 's';
+const RENAME_COPY:           char = 'r';
+const OVERWRITE_IF_NEWER:    char = 'n';
+const APPLY_TO_ALL:          char = 'a';
+const BOOKMARKS_VIEW:        char = 'M';
+const DELETE_BOOKMARK:       char = 'd';
 
+#[allow(clippy::too_many_arguments)]
 pub fn handle_normal_mode(
     key_code: KeyCode,
     modifiers: KeyModifiers,
     current_dir: &mut std::path::PathBuf,
     middle_state: &mut ListState,
-    left_state: &mut ListState,
     files: &[FileInfo],
     scroll_position: &mut usize,
     max_scroll: &usize,
@@ -41,35 +40,104 @@ pub fn handle_normal_mode(
 ) -> bool {
     app_state.prompt_message = None;
 
-    match (key_code, modifiers) {
-        (KeyCode::Char(MOVE_IN),_)               => move_in(current_dir, middle_state, files,app_state),
-        (KeyCode::Char(MOVE_OUT),_)              => move_out(current_dir, middle_state, left_state),
-        (KeyCode::Char(MOVE_UP), _)              => move_up(middle_state,files.len(),scroll_position, app_state),
-        (KeyCode::Char(MOVE_DOWN),_)             => move_down(middle_state,files.len(), scroll_position, max_scroll,app_state),
-        (KeyCode::Char(MOVE_DOWN_HALF_PAGE), _)  => move_down_half(middle_state, files.len(), scroll_position, max_scroll, app_state),
-        (KeyCode::Char(MOVE_UP_HALF_PAGE), _)    => move_up_half(middle_state, files.len(), scroll_position, app_state),
-        (KeyCode::Char(CREATE_FILE), _)          => handle_create_file(app_state),
-        (KeyCode::Char(CREATE_DIR), _)           => handle_create_directory(app_state),
-        (KeyCode::Char(COPY), _)                 => copy_file(current_dir, middle_state, files, app_state),
-        (KeyCode::Char(CUT), _)                  => cut_file(current_dir, middle_state, files, app_state),
-        (KeyCode::Char(PASTE), _)                => paste_file(current_dir, app_state),
-        (KeyCode::Char(DELETE), _)               => handle_delete(middle_state, files, app_state),
-        (KeyCode::Char(RENAME), _)               => handle_rename(middle_state, files, app_state),
-        (KeyCode::Char(CHANGE_PERMISSIONS), _)   => handle_change_permissions(middle_state, files, app_state),
-        (KeyCode::Char(GO_TO_TOP), _)            => go_to_top(middle_state, app_state, scroll_position),
-        (KeyCode::Char(GO_TO_BOTTOM), _)         => go_to_bottom(middle_state,app_state, files.len(), scroll_position, max_scroll),
-        (KeyCode::Char(SEARCH), _)               => handle_search(app_state),
-        (KeyCode::Char(NEXT), _)                 => next_search(middle_state, files, app_state),
-        (KeyCode::Char(PREVIOUS), _)             => previous_search(middle_state, files, app_state),
-        (KeyCode::Char(QUIT), _)                 => return handle_quit(),
-        _                                        => { app_state.last_key_pressed = None; app_state.last_modifier = None; },
+    let Some(action) = next_action(KeyPress::new(key_code, modifiers), app_state) else {
+        return false;
+    };
+
+    match action {
+        Action::MoveIn              => move_in(current_dir, middle_state, files,app_state),
+        Action::MoveOut             => move_out(current_dir, middle_state, files, app_state),
+        Action::GoBack              => go_back(current_dir, middle_state, files, app_state),
+        Action::GoForward           => go_forward(current_dir, middle_state, files, app_state),
+        Action::MoveUp              => move_up(middle_state, files.len()),
+        Action::MoveDown            => move_down(middle_state, files.len()),
+        Action::HalfPageDown        => move_down_half(middle_state, files.len(), app_state),
+        Action::HalfPageUp          => move_up_half(middle_state, files.len(), app_state),
+        Action::GoToTop             => go_to_top(middle_state),
+        Action::GoToBottom          => go_to_bottom(middle_state, files.len()),
+        Action::NewTab              => new_tab(current_dir, middle_state, scroll_position, app_state),
+        Action::DuplicateTab        => duplicate_tab(current_dir, middle_state, scroll_position, app_state),
+        Action::CloseTab            => close_tab(current_dir, middle_state, scroll_position, app_state),
+        Action::NextTab             => next_tab(current_dir, middle_state, scroll_position, app_state),
+        Action::PreviousTab         => previous_tab(current_dir, middle_state, scroll_position, app_state),
+        Action::ToggleDualPane      => toggle_dual_pane(current_dir, middle_state, scroll_position, app_state),
+        Action::SwitchPanel         => switch_panel(current_dir, middle_state, scroll_position, app_state),
+        Action::PreviewDown         => scroll_preview_down(scroll_position, max_scroll),
+        Action::PreviewUp           => scroll_preview_up(scroll_position),
+        Action::PreviewHalfPageDown => scroll_preview_down_half(scroll_position, max_scroll, app_state),
+        Action::PreviewHalfPageUp   => scroll_preview_up_half(scroll_position, app_state),
+        Action::PreviewTop          => scroll_preview_to_top(scroll_position),
+        Action::PreviewBottom       => scroll_preview_to_bottom(scroll_position, max_scroll),
+        Action::Undo                => undo(app_state),
+        Action::Redo                => redo(app_state),
+        Action::CreateFile          => handle_create_file(app_state),
+        Action::CreateDir           => handle_create_directory(app_state),
+        Action::Copy                => copy_file(current_dir, middle_state, files, app_state),
+        Action::Cut                 => cut_file(current_dir, middle_state, files, app_state),
+        Action::Paste               => paste_file(current_dir, app_state),
+        Action::CopyToOtherPanel    => transfer_to_other_panel(current_dir, middle_state, files, app_state, false),
+        Action::MoveToOtherPanel    => transfer_to_other_panel(current_dir, middle_state, files, app_state, true),
+        Action::PasteSymlink        => paste_links(current_dir, app_state, LinkKind::AbsoluteSymlink),
+        Action::PasteRelativeSymlink => paste_links(current_dir, app_state, LinkKind::RelativeSymlink),
+        Action::PasteHardLink       => paste_links(current_dir, app_state, LinkKind::HardLink),
+        Action::SetBookmark         => start_bookmark_command(app_state, BookmarkCommand::Set),
+        Action::JumpToBookmark      => start_bookmark_command(app_state, BookmarkCommand::Jump),
+        Action::BookmarksView       => open_bookmarks_view(app_state),
+        Action::Delete              => handle_delete(current_dir, middle_state, files, app_state, false),
+        Action::PermanentDelete     => handle_delete(current_dir, middle_state, files, app_state, true),
+        Action::TrashView           => open_trash_view(app_state),
+        Action::JobsView            => open_jobs_view(app_state),
+        Action::Rename              => handle_rename(middle_state, files, app_state),
+        Action::ChangePermissions   => handle_change_permissions(current_dir, middle_state, files, app_state),
+        Action::Compress            => handle_compress(current_dir, middle_state, files, app_state),
+        Action::Extract             => extract_archives(current_dir, middle_state, files, app_state),
+        Action::ToggleMark          => toggle_mark(current_dir, middle_state, files, app_state),
+        Action::InvertMarks         => invert_marks(current_dir, files, app_state),
+        Action::MarkAll             => mark_all(current_dir, files, app_state),
+        Action::ClearMarks          => clear_marks(app_state),
+        Action::CycleSort           => cycle_sort_mode(middle_state, files, app_state),
+        Action::ReverseSort         => toggle_sort_reverse(middle_state, files, app_state),
+        Action::ToggleDirsFirst     => toggle_dirs_first(middle_state, files, app_state),
+        Action::ToggleHidden        => toggle_hidden(middle_state, files, app_state),
+        Action::ToggleGitignore     => toggle_gitignore(middle_state, files, app_state),
+        Action::ToggleFollowSymlinks => toggle_follow_symlinks(app_state),
+        Action::Search              => handle_search(app_state),
+        Action::NextMatch           => next_search(middle_state, files, app_state),
+        Action::PreviousMatch       => previous_search(middle_state, files, app_state),
+        Action::Quit                => return handle_quit(),
     }
     false
 }
 
+// Feed a key into the pending sequence and return the action once a whole binding has been typed.
+// A key that can't continue the sequence starts a new one, so "gj" still moves down.
+fn next_action(key: KeyPress, app_state: &mut AppState) -> Option<Action> {
+    app_state.pending_keys.push(key);
+
+    loop {
+        match app_state.config.keymap.lookup(&app_state.pending_keys) {
+            KeyLookup::Action(action) => {
+                app_state.pending_keys.clear();
+                return Some(action);
+            }
+            KeyLookup::Pending => return None,
+            KeyLookup::Unbound if app_state.pending_keys.len() > 1 => {
+                app_state.pending_keys = vec![key];
+            }
+            KeyLookup::Unbound => {
+                app_state.pending_keys.clear();
+                return None;
+            }
+        }
+    }
+}
+
+#[allow(clippy::ptr_arg)]
 pub fn handle_creation_mode(
     key_code: KeyCode,
     current_dir: &std::path::PathBuf,
+    middle_state: &ListState,
+    files: &[FileInfo],
     app_state: &mut AppState,
 ) -> bool {
     match key_code {
@@ -81,6 +149,10 @@ pub fn handle_creation_mode(
                 buffer.pop();
             }
         },
+        KeyCode::Enter if app_state.is_creating_archive => {
+            let archive_name = app_state.creation_buffer.take().unwrap_or_default();
+            compress_files(current_dir, middle_state, files, app_state, &archive_name);
+        },
         KeyCode::Enter => {
             if let Some(new_name) = &app_state.creation_buffer {
                 let new_path = current_dir.join(new_name);
@@ -90,10 +162,14 @@ pub fn handle_creation_mode(
                     if app_state.is_creating_file {
                         if std::fs::File::create(&new_path).is_err() {
                             app_state.prompt_message = Some(format!(" Failed to create file: {}.", new_name));
+                        } else {
+                            app_state.journal.record(vec![Operation::Trash { path: new_path }]);
                         }
                     } else if app_state.is_creating_directory {
                         if std::fs::create_dir(&new_path).is_err() {
                             app_state.prompt_message = Some(format!(" Failed to create directory: {}.", new_name));
+                        } else {
+                            app_state.journal.record(vec![Operation::Trash { path: new_path }]);
                         }
                     }
                 }
@@ -105,6 +181,7 @@ pub fn handle_creation_mode(
         KeyCode::Esc => {
             app_state.is_creating_file = false;
             app_state.is_creating_directory = false;
+            app_state.is_creating_archive = false;
             app_state.prompt_message = None;
             app_state.creation_buffer = None;
         },
@@ -162,8 +239,8 @@ pub fn handle_delete_mode(
 ) -> bool {
     match key_code {
         KeyCode::Char(YES) => {
-            delete_file(current_dir, middle_state, files, app_state);
             app_state.prompt_message = None;
+            delete_file(current_dir, middle_state, files, app_state);
             app_state.delete_mode = false;
         },
         KeyCode::Char(NO) => {
@@ -175,6 +252,101 @@ pub fn handle_delete_mode(
     false
 }
 
+pub fn handle_trash_mode(key_code: KeyCode, app_state: &mut AppState) -> bool {
+    if app_state.is_emptying_trash {
+        match key_code {
+            KeyCode::Char(YES) => empty_trash(app_state),
+            KeyCode::Char(NO) | KeyCode::Esc => {
+                app_state.is_emptying_trash = false;
+                app_state.prompt_message = None;
+            },
+            _ => {}
+        }
+        return false;
+    }
+
+    app_state.prompt_message = None;
+    let entries_len = app_state.trash_entries.len();
+
+    match key_code {
+        KeyCode::Char(MOVE_DOWN) | KeyCode::Down => adjust_selection(&mut app_state.trash_state, entries_len, true),
+        KeyCode::Char(MOVE_UP) | KeyCode::Up     => adjust_selection(&mut app_state.trash_state, entries_len, false),
+        KeyCode::Char(RESTORE)                   => restore_from_trash(app_state),
+        KeyCode::Char(EMPTY_TRASH)               => handle_empty_trash(app_state),
+        KeyCode::Char(TRASH_VIEW) | KeyCode::Char(QUIT) | KeyCode::Esc => close_trash_view(app_state),
+        _ => {}
+    }
+    false
+}
+
+pub fn handle_bookmark_letter(
+    key_code: KeyCode,
+    command: BookmarkCommand,
+    current_dir: &mut std::path::PathBuf,
+    middle_state: &mut ListState,
+    files: &[FileInfo],
+    app_state: &mut AppState,
+) -> bool {
+    app_state.prompt_message = None;
+
+    match (key_code, command) {
+        (KeyCode::Char(letter), BookmarkCommand::Set) if is_bookmark_letter(letter) => set_bookmark(current_dir, app_state, letter),
+        (KeyCode::Char(letter), BookmarkCommand::Jump) if is_bookmark_letter(letter) => jump_to_bookmark(current_dir, middle_state, files, app_state, letter),
+        (KeyCode::Esc, _) => {}
+        (_, _) => app_state.prompt_message = Some(" Bookmarks are named by a letter".to_string()),
+    }
+    false
+}
+
+pub fn handle_bookmarks_mode(
+    key_code: KeyCode,
+    current_dir: &mut std::path::PathBuf,
+    middle_state: &mut ListState,
+    files: &[FileInfo],
+    app_state: &mut AppState,
+) -> bool {
+    app_state.prompt_message = None;
+    let bookmarks_len = app_state.bookmarks.len();
+
+    match key_code {
+        KeyCode::Char(MOVE_DOWN) | KeyCode::Down => adjust_selection(&mut app_state.bookmarks_state, bookmarks_len, true),
+        KeyCode::Char(MOVE_UP) | KeyCode::Up     => adjust_selection(&mut app_state.bookmarks_state, bookmarks_len, false),
+        KeyCode::Char(DELETE_BOOKMARK)           => delete_selected_bookmark(app_state),
+        KeyCode::Enter                           => jump_to_selected_bookmark(current_dir, middle_state, files, app_state),
+        KeyCode::Char(BOOKMARKS_VIEW) | KeyCode::Char(QUIT) | KeyCode::Esc => close_bookmarks_view(app_state),
+        _ => {}
+    }
+    false
+}
+
+pub fn handle_jobs_mode(key_code: KeyCode, app_state: &mut AppState) -> bool {
+    app_state.prompt_message = None;
+    let jobs_len = app_state.jobs.jobs().len();
+
+    match key_code {
+        KeyCode::Char(MOVE_DOWN) | KeyCode::Down => adjust_selection(&mut app_state.jobs_state, jobs_len, true),
+        KeyCode::Char(MOVE_UP) | KeyCode::Up     => adjust_selection(&mut app_state.jobs_state, jobs_len, false),
+        KeyCode::Char(CANCEL_JOB)                => cancel_job(app_state),
+        KeyCode::Char(JOBS_VIEW) | KeyCode::Char(QUIT) | KeyCode::Esc => close_jobs_view(app_state),
+        _ => {}
+    }
+    false
+}
+
+pub fn handle_conflict_mode(key_code: KeyCode, app_state: &mut AppState) -> bool {
+    match key_code {
+        KeyCode::Char(OVERWRITE)          => resolve_paste_conflict(app_state, ConflictPolicy::Overwrite),
+        KeyCode::Char(SKIP)               => resolve_paste_conflict(app_state, ConflictPolicy::Skip),
+        KeyCode::Char(RENAME_COPY)        => resolve_paste_conflict(app_state, ConflictPolicy::Rename),
+        KeyCode::Char(OVERWRITE_IF_NEWER) => resolve_paste_conflict(app_state, ConflictPolicy::OverwriteIfNewer),
+        KeyCode::Char(APPLY_TO_ALL)       => toggle_apply_to_all(app_state),
+        KeyCode::Esc                      => cancel_paste(app_state),
+        _ => {}
+    }
+    false
+}
+
+#[allow(clippy::ptr_arg)]
 pub fn handle_renaming_mode(
     key_code: KeyCode,
     current_dir: &std::path::PathBuf,
@@ -200,7 +372,9 @@ pub fn handle_renaming_mode(
                     if new_file_path.exists() {
                         app_state.prompt_message = Some(" Error: File with this name already exists!".to_string());
                     } else if let Err(e) = std::fs::rename(&file_path, &new_file_path) {
-                        app_state.prompt_message = Some(format!(" Failed to rename {}: {}.", &files[index].name, e));
+                        app_state.prompt_message = Some(format!(" Failed to rename {}: {}.", files[index].display_name(), e));
+                    } else {
+                        app_state.journal.record(vec![Operation::Move { src: new_file_path, dest: file_path }]);
                     }
 
                     app_state.rename_mode = false;
@@ -219,6 +393,7 @@ pub fn handle_renaming_mode(
 }
 
 #[cfg(target_family = "unix")]
+#[allow(clippy::ptr_arg)]
 pub fn handle_permissions_mode(
     key_code: KeyCode,
     current_dir: &std::path::PathBuf,
@@ -226,10 +401,8 @@ pub fn handle_permissions_mode(
     files: &[FileInfo],
     app_state: &mut AppState,
 ) -> bool {
-    use std::os::unix::fs::PermissionsExt;
-
     match key_code {
-        KeyCode::Char(c) if c.is_digit(10) && app_state.permissions_buffer.as_ref().unwrap().len() < 3 => {
+        KeyCode::Char(c) if c.is_ascii_digit() && app_state.permissions_buffer.as_ref().unwrap().len() < 3 => {
             app_state.permissions_buffer.get_or_insert_with(String::new).push(c);
         },
         KeyCode::Backspace => {
@@ -238,20 +411,25 @@ pub fn handle_permissions_mode(
             }
         },
         KeyCode::Enter => {
-            if let Some(index) = middle_state.selected() {
-                let file_path = current_dir.join(&files[index].name);
-                if let Some(permission_str) = &app_state.permissions_buffer {
-                    if let Ok(mode) = u32::from_str_radix(permission_str, 8) {
-                        let permissions = std::fs::Permissions::from_mode(mode);
-                        if let Err(e) = std::fs::set_permissions(&file_path, permissions) {
-                            app_state.prompt_message = Some(format!(" Failed to set permissions for {}: {}.", &files[index].name, e));
+            if let Some(permission_str) = &app_state.permissions_buffer {
+                if let Ok(mode) = u32::from_str_radix(permission_str, 8) {
+                    let mut undo_operations = Vec::new();
+                    for file_path in get_target_files(current_dir, middle_state, files, app_state) {
+                        match (Operation::Chmod { path: file_path.clone(), mode }).perform() {
+                            Ok(undo_operation) => undo_operations.push(undo_operation),
+                            Err(e) => {
+                                let file_name = file_path.file_name().unwrap_or_default().to_string_lossy();
+                                app_state.prompt_message = Some(format!(" Failed to set permissions for {}: {}.", file_name, e));
+                            }
                         }
-                    } else {
-                        app_state.prompt_message = Some(" Invalid permission value!".to_string());
                     }
-                    app_state.is_changing_permissions = false;
-                    app_state.permissions_buffer = None;
+                    app_state.journal.record(undo_operations);
+                    app_state.marked_files.clear();
+                } else {
+                    app_state.prompt_message = Some(" Invalid permission value!".to_string());
                 }
+                app_state.is_changing_permissions = false;
+                app_state.permissions_buffer = None;
             }
         },
         KeyCode::Esc => {
@@ -0,0 +1,293 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+
+use crate::fs_utils::{self, ConflictPolicy};
+use crate::archive;
+use crate::journal::Operation;
+use crate::trash::Trash;
+
+/// Work that is too slow to do on the UI thread. Copies and moves carry the policy
+/// for a destination that already exists (or that shows up while the job is queued).
+pub enum JobTask {
+    Copy(Vec<(PathBuf, PathBuf, ConflictPolicy)>),
+    Move(Vec<(PathBuf, PathBuf, ConflictPolicy)>),
+    Delete(Vec<PathBuf>),
+    Trash(Vec<PathBuf>),
+    // The sources and the archive to pack them into
+    Compress(Vec<PathBuf>, PathBuf),
+    // Each archive and the directory to unpack it into
+    Extract(Vec<(PathBuf, PathBuf)>),
+}
+
+impl JobTask {
+    fn describe(&self) -> String {
+        let (verb, count, first) = match self {
+            JobTask::Copy(items)  => ("Copying", items.len(), items.first().map(|(src, _, _)| src)),
+            JobTask::Move(items)  => ("Moving", items.len(), items.first().map(|(src, _, _)| src)),
+            JobTask::Delete(paths) => ("Deleting", paths.len(), paths.first()),
+            JobTask::Trash(paths)  => ("Trashing", paths.len(), paths.first()),
+            JobTask::Compress(sources, _) => ("Compressing", sources.len(), sources.first()),
+            JobTask::Extract(items) => ("Extracting", items.len(), items.first().map(|(archive, _)| archive)),
+        };
+
+        match (count, first) {
+            (1, Some(path)) => format!("{} {}", verb, path.file_name().unwrap_or_default().to_string_lossy()),
+            _ => format!("{} {} items", verb, count),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default, Debug)]
+pub struct JobProgress {
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+    pub files_done: u64,
+    pub files_total: u64,
+}
+
+impl JobProgress {
+    pub fn ratio(&self) -> f64 {
+        if self.bytes_total > 0 {
+            (self.bytes_done as f64 / self.bytes_total as f64).min(1.0)
+        } else if self.files_total > 0 {
+            (self.files_done as f64 / self.files_total as f64).min(1.0)
+        } else {
+            0.0
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "{}/{} files, {}/{}",
+            self.files_done,
+            self.files_total,
+            fs_utils::format_size(self.bytes_done),
+            fs_utils::format_size(self.bytes_total)
+        )
+    }
+}
+
+/// A job that is queued or running, as seen from the UI thread.
+pub struct JobStatus {
+    pub id: usize,
+    pub description: String,
+    pub progress: JobProgress,
+    pub started: bool,
+    cancelled: Arc<AtomicBool>,
+}
+
+/// What a job left behind: the operations that undo the parts that completed and the first error, if any.
+pub struct JobOutcome {
+    pub description: String,
+    pub undo_operations: Vec<Operation>,
+    pub error: Option<String>,
+    pub cancelled: bool,
+}
+
+struct QueuedJob {
+    id: usize,
+    task: JobTask,
+    cancelled: Arc<AtomicBool>,
+}
+
+enum JobEvent {
+    Started(usize, JobProgress),
+    Progress(usize, JobProgress),
+    Finished(usize, JobOutcome),
+}
+
+/// Runs jobs one at a time on a worker thread and collects their progress over a channel.
+pub struct JobManager {
+    sender: Sender<QueuedJob>,
+    events: Receiver<JobEvent>,
+    jobs: Vec<JobStatus>,
+    next_id: usize,
+}
+
+impl JobManager {
+    pub fn new() -> Self {
+        let (sender, queue) = mpsc::channel::<QueuedJob>();
+        let (event_sender, events) = mpsc::channel();
+
+        thread::spawn(move || {
+            for job in queue {
+                run_job(job, &event_sender);
+            }
+        });
+
+        JobManager {
+            sender,
+            events,
+            jobs: Vec::new(),
+            next_id: 0,
+        }
+    }
+
+    pub fn submit(&mut self, task: JobTask) {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.jobs.push(JobStatus {
+            id,
+            description: task.describe(),
+            progress: JobProgress::default(),
+            started: false,
+            cancelled: Arc::clone(&cancelled),
+        });
+        let _ = self.sender.send(QueuedJob { id, task, cancelled });
+    }
+
+    pub fn cancel(&mut self, id: usize) {
+        if let Some(job) = self.jobs.iter().find(|job| job.id == id) {
+            job.cancelled.store(true, Ordering::Relaxed);
+        }
+    }
+
+    pub fn jobs(&self) -> &[JobStatus] {
+        &self.jobs
+    }
+
+    pub fn is_busy(&self) -> bool {
+        !self.jobs.is_empty()
+    }
+
+    /// Apply the progress reported since the last call and return the jobs that have finished.
+    pub fn poll(&mut self) -> Vec<JobOutcome> {
+        let mut finished = Vec::new();
+
+        for event in self.events.try_iter() {
+            match event {
+                JobEvent::Started(id, progress) | JobEvent::Progress(id, progress) => {
+                    if let Some(job) = self.jobs.iter_mut().find(|job| job.id == id) {
+                        job.progress = progress;
+                        job.started = true;
+                    }
+                }
+                JobEvent::Finished(id, outcome) => {
+                    self.jobs.retain(|job| job.id != id);
+                    finished.push(outcome);
+                }
+            }
+        }
+        finished
+    }
+}
+
+impl Default for JobManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn run_job(job: QueuedJob, events: &Sender<JobEvent>) {
+    let description = job.task.describe();
+    let sizes: Vec<(u64, u64)> = match &job.task {
+        JobTask::Copy(items) | JobTask::Move(items) => items.iter().map(|(src, _, _)| fs_utils::get_tree_size(src)).collect(),
+        JobTask::Delete(paths) | JobTask::Trash(paths) => paths.iter().map(|path| fs_utils::get_tree_size(path)).collect(),
+        // All the sources go into a single archive, so they are one item
+        JobTask::Compress(sources, _) => vec![
+            sources.iter()
+                .map(|src| fs_utils::get_tree_size(src))
+                .fold((0, 0), |(bytes, files), (src_bytes, src_files)| (bytes + src_bytes, files + src_files)),
+        ],
+        JobTask::Extract(items) => items.iter().map(|(archive, _)| archive::unpacked_size(archive)).collect(),
+    };
+
+    let mut progress = JobProgress {
+        bytes_total: sizes.iter().map(|(bytes, _)| bytes).sum(),
+        files_total: sizes.iter().map(|(_, files)| files).sum(),
+        ..Default::default()
+    };
+    let _ = events.send(JobEvent::Started(job.id, progress));
+
+    let mut undo_operations = Vec::new();
+    let mut error = None;
+
+    for (index, (item_bytes, item_files)) in sizes.iter().copied().enumerate() {
+        if job.cancelled.load(Ordering::Relaxed) {
+            break;
+        }
+
+        let mut report = |bytes: u64, files: u64| {
+            progress.bytes_done += bytes;
+            progress.files_done += files;
+            let _ = events.send(JobEvent::Progress(job.id, progress));
+            !job.cancelled.load(Ordering::Relaxed)
+        };
+
+        let result = match &job.task {
+            JobTask::Copy(items) => {
+                let (src, dest, policy) = &items[index];
+                let dest_existed = dest.symlink_metadata().is_ok();
+                fs_utils::copy_with_progress(src, dest, *policy, &mut report)
+                    .map(|copied| {
+                        // Anything that was overwritten or merged into can't be taken back out again
+                        copied
+                            .filter(|actual_dest| !dest_existed || actual_dest != dest)
+                            .map(|actual_dest| Operation::RemoveCopy { src: src.clone(), dest: actual_dest })
+                    })
+            }
+            JobTask::Move(items) => {
+                let (src, dest, policy) = &items[index];
+                let dest_existed = dest.symlink_metadata().is_ok();
+                fs_utils::move_with_progress(src, dest, *policy, &mut report)
+                    .map(|moved| {
+                        moved
+                            .filter(|actual_dest| !dest_existed || actual_dest != dest)
+                            .map(|actual_dest| Operation::Move { src: actual_dest, dest: src.clone() })
+                    })
+            }
+            JobTask::Delete(paths) => {
+                fs_utils::delete(&paths[index]).map(|_| {
+                    report(item_bytes, item_files);
+                    None
+                })
+            }
+            JobTask::Trash(paths) => {
+                // Only a copy when the file system `path` is on has no usable trash of its own
+                let path = &paths[index];
+                Trash::for_path(path).and_then(|trash| trash.trash(path)).map(|entry| {
+                    report(item_bytes, item_files);
+                    Some(Operation::Restore { entry })
+                })
+            }
+            JobTask::Compress(sources, archive) => {
+                // Picked only now in case something took the name while the job was queued
+                let archive = fs_utils::make_unique_path(archive.clone());
+                archive::create_archive(sources, &archive, &mut report)
+                    .map(|_| Some(Operation::Trash { path: archive.clone() }))
+                    .inspect_err(|_| {
+                        let _ = fs_utils::delete(&archive);
+                    })
+            }
+            JobTask::Extract(items) => {
+                let (archive, dest) = &items[index];
+                let dest = fs_utils::make_unique_path(dest.clone());
+                archive::extract_archive(archive, &dest, &mut report)
+                    .map(|_| Some(Operation::Trash { path: dest.clone() }))
+                    .inspect_err(|_| {
+                        let _ = fs_utils::delete(&dest);
+                    })
+            }
+        };
+
+        match result {
+            Ok(undo_operation) => undo_operations.extend(undo_operation),
+            Err(e) if fs_utils::is_cancelled(&e) => break,
+            Err(e) => {
+                error.get_or_insert_with(|| e.to_string());
+            }
+        }
+    }
+
+    let _ = events.send(JobEvent::Finished(job.id, JobOutcome {
+        description,
+        undo_operations,
+        error,
+        cancelled: job.cancelled.load(Ordering::Relaxed),
+    }));
+}
@@ -0,0 +1,148 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::fs_utils;
+use crate::trash::{Trash, TrashEntry};
+
+/// A reversible file operation. Performing one returns the operation that reverses it,
+/// which is what lets the journal move entries back and forth between undo and redo.
+#[derive(Clone, Debug)]
+pub enum Operation {
+    Copy { src: PathBuf, dest: PathBuf },
+    RemoveCopy { src: PathBuf, dest: PathBuf },
+    Move { src: PathBuf, dest: PathBuf },
+    Trash { path: PathBuf },
+    Restore { entry: TrashEntry },
+    Chmod { path: PathBuf, mode: u32 },
+}
+
+impl Operation {
+    pub fn perform(&self) -> io::Result<Operation> {
+        match self {
+            Operation::Copy { src, dest } => {
+                if dest.symlink_metadata().is_ok() {
+                    return Err(already_exists(dest));
+                }
+                fs_utils::copy(src, dest)?;
+                Ok(Operation::RemoveCopy { src: src.clone(), dest: dest.clone() })
+            }
+            Operation::RemoveCopy { src, dest } => {
+                fs_utils::delete(dest)?;
+                Ok(Operation::Copy { src: src.clone(), dest: dest.clone() })
+            }
+            Operation::Move { src, dest } => {
+                if dest.symlink_metadata().is_ok() {
+                    return Err(already_exists(dest));
+                }
+                fs_utils::move_file(src, dest)?;
+                Ok(Operation::Move { src: dest.clone(), dest: src.clone() })
+            }
+            Operation::Trash { path } => {
+                let entry = Trash::for_path(path)?.trash(path)?;
+                Ok(Operation::Restore { entry })
+            }
+            Operation::Restore { entry } => {
+                let path = Trash::new(&entry.trash_dir).restore(entry)?;
+                Ok(Operation::Trash { path })
+            }
+            Operation::Chmod { path, mode } => set_mode(path, *mode),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Operation::Copy { dest, .. }       => format!("copy to {}", dest.display()),
+            Operation::RemoveCopy { dest, .. } => format!("remove copy {}", dest.display()),
+            Operation::Move { src, dest }      => format!("move {} to {}", src.display(), dest.display()),
+            Operation::Trash { path }          => format!("trash {}", path.display()),
+            Operation::Restore { entry }       => format!("restore {}", entry.original_path.display()),
+            Operation::Chmod { path, mode }    => format!("chmod {:o} {}", mode, path.display()),
+        }
+    }
+}
+
+fn already_exists(path: &std::path::Path) -> io::Error {
+    io::Error::new(io::ErrorKind::AlreadyExists, format!("{} already exists", path.display()))
+}
+
+#[cfg(unix)]
+fn set_mode(path: &std::path::Path, mode: u32) -> io::Result<Operation> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let old_mode = fs::metadata(path)?.permissions().mode() & 0o7777;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+    Ok(Operation::Chmod { path: path.to_path_buf(), mode: old_mode })
+}
+
+#[cfg(windows)]
+fn set_mode(_path: &std::path::Path, _mode: u32) -> io::Result<Operation> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "Changing permissions is not supported on this platform"))
+}
+
+/// Undo and redo history. Each entry holds the operations that reverse one user command,
+/// so a batch over marked files is undone in a single step.
+#[derive(Default)]
+pub struct Journal {
+    undo_stack: Vec<Vec<Operation>>,
+    redo_stack: Vec<Vec<Operation>>,
+}
+
+impl Journal {
+    pub fn record(&mut self, undo_operations: Vec<Operation>) {
+        if !undo_operations.is_empty() {
+            self.undo_stack.push(undo_operations);
+            self.redo_stack.clear();
+        }
+    }
+
+    pub fn undo(&mut self) -> Option<io::Result<String>> {
+        let operations = self.undo_stack.pop()?;
+        let (inverse, remaining, result) = perform_all(operations);
+        if !inverse.is_empty() {
+            self.redo_stack.push(inverse);
+        }
+        if !remaining.is_empty() {
+            self.undo_stack.push(remaining);
+        }
+        Some(result)
+    }
+
+    pub fn redo(&mut self) -> Option<io::Result<String>> {
+        let operations = self.redo_stack.pop()?;
+        let (inverse, remaining, result) = perform_all(operations);
+        if !inverse.is_empty() {
+            self.undo_stack.push(inverse);
+        }
+        if !remaining.is_empty() {
+            self.redo_stack.push(remaining);
+        }
+        Some(result)
+    }
+}
+
+// Perform the operations in reverse order of recording, stopping at the first failure.
+// Whatever did succeed is returned so it can be reverted in turn, and the failed operation
+// along with the ones after it are returned so that undoing or redoing again retries them.
+fn perform_all(mut operations: Vec<Operation>) -> (Vec<Operation>, Vec<Operation>, io::Result<String>) {
+    let description = match operations.as_slice() {
+        [single] => single.describe(),
+        _ => format!("{} operations", operations.len()),
+    };
+
+    let mut inverse = Vec::new();
+    while let Some(operation) = operations.last() {
+        match operation.perform() {
+            Ok(reverse) => {
+                inverse.push(reverse);
+                operations.pop();
+            }
+            Err(e) if inverse.is_empty() => return (inverse, operations, Err(e)),
+            Err(e) => {
+                let error = io::Error::new(e.kind(), format!("{} ({} of {} left to retry)", e, operations.len(), operations.len() + inverse.len()));
+                return (inverse, operations, Err(error));
+            }
+        }
+    }
+    (inverse, operations, Ok(description))
+}
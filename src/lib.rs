@@ -3,4 +3,16 @@ pub mod fs_utils;
 pub mod input;
 pub mod preview;
 pub mod ui;
-pub mod state;
\ No newline at end of file
+pub mod state;
+pub mod sort;
+pub mod trash;
+pub mod journal;
+pub mod jobs;
+pub mod config;
+pub mod filter;
+pub mod highlight;
+pub mod archive;
+pub mod dir_cache;
+pub mod bookmarks;
+pub mod history;
+pub mod tabs;
\ No newline at end of file
@@ -15,10 +15,24 @@ mod input;
 mod preview;
 mod input_handlers;
 mod state;
-
-use ui::{render_pane, PaneType};
+mod sort;
+mod trash;
+mod journal;
+mod jobs;
+mod config;
+mod filter;
+mod highlight;
+mod archive;
+mod dir_cache;
+mod bookmarks;
+mod history;
+mod tabs;
+
+use ui::{render_pane, render_list_view, render_job_progress, render_tab_bar, status_line, PaneType};
 use fs_utils::*;
 use input::*;
+use sort::sort_files;
+use input_handlers::file_manipulation::collect_finished_jobs;
 
 fn main() {
     // Initialize crossterm
@@ -42,11 +56,50 @@ fn main() {
     middle_state.select(Some(0));
 
     loop {
-        let parents = get_parent_content(&current_dir);
-        let files = get_files_and_dirs(&current_dir);
+        collect_finished_jobs(&mut app_state);
+
+        // The dual-pane layout has no parent or preview column, so neither is read while it is on
+        let dual_pane = app_state.other_panel.is_some();
+        let mut parents = if dual_pane { Vec::new() } else { get_parent_content(&current_dir) };
+        let mut files = get_files_and_dirs(&current_dir);
+        if let Some(parent_dir) = current_dir.parent() {
+            app_state.file_filter.apply_keeping(parent_dir, &mut parents, current_dir.file_name());
+        }
+        app_state.file_filter.apply(&current_dir, &mut files);
+        sort_files(&mut parents, &app_state.sort_options);
+        sort_files(&mut files, &app_state.sort_options);
+
+        if let Some(name) = app_state.select_on_refresh.take() {
+            if let Some(index) = files.iter().position(|file_info| file_info.name == name) {
+                middle_state.select(Some(index));
+            }
+        }
+        if middle_state.selected().is_some_and(|index| index >= files.len()) {
+            middle_state.select(Some(files.len().saturating_sub(1)));
+        }
+        // The parent listing highlights the directory we are in
+        left_state.select(current_dir.file_name().and_then(|name| parents.iter().position(|file_info| file_info.name == name)));
         update_selected_dir(&files, &current_dir, &mut selected_dir, &middle_state, &mut scroll_position);
 
-        let (children, max_scroll) = fetch_children(&selected_dir, scroll_position, app_state.terminal_height);
+        let (mut children, max_scroll) = if dual_pane {
+            (Vec::new(), 0)
+        } else {
+            fetch_children(&selected_dir, scroll_position, app_state.terminal_height, &app_state.file_filter)
+        };
+        if !dual_pane && selected_dir.is_dir() {
+            sort_files(&mut children, &app_state.sort_options);
+        }
+
+        // The inactive panel of the dual-pane layout is listed with its own sorting and filters
+        let mut other_files = Vec::new();
+        if let Some(panel) = app_state.other_panel.as_mut() {
+            other_files = get_files_and_dirs(&panel.current_dir);
+            panel.file_filter.apply(&panel.current_dir, &mut other_files);
+            sort_files(&mut other_files, &panel.sort_options);
+            if panel.middle_state.selected().is_some_and(|index| index >= other_files.len()) {
+                panel.middle_state.select(Some(other_files.len().saturating_sub(1)));
+            }
+        }
 
         // Render UI
         terminal.draw(|f| {
@@ -62,21 +115,71 @@ fn main() {
                 )
                 .split(f.size());
 
-            let horizontal_chunks = Layout::default()
-                .direction(Direction::Horizontal)
-                .constraints(
-                    [
-                        Constraint::Percentage(20),
-                        Constraint::Percentage(30),
-                        Constraint::Percentage(50),
-                    ]
-                    .as_ref(),
-                )
-                .split(vertical_chunks[0]);
-
-            render_pane(f, horizontal_chunks[0], &parents, &mut left_state, PaneType::Left);
-            render_pane(f, horizontal_chunks[1], &files, &mut middle_state, PaneType::Middle);
-            render_pane(f, horizontal_chunks[2], &children, &mut right_state, PaneType::Right);
+            // The tab bar only takes up room once there is more than one tab
+            let mut top_chunk = vertical_chunks[0];
+            if app_state.tabs.count() > 1 {
+                let tab_chunks = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Length(1), Constraint::Min(0)].as_ref())
+                    .split(top_chunk);
+                render_tab_bar(f, tab_chunks[0], &app_state.tabs.labels(&current_dir), app_state.tabs.active(), &app_state.config.theme);
+                top_chunk = tab_chunks[1];
+            }
+
+            if app_state.jobs_view {
+                let jobs: Vec<String> = app_state.jobs.jobs().iter()
+                    .map(|job| {
+                        let state = if job.started { "running" } else { "queued" };
+                        format!("[{}] {}  {:.0}%  {}", state, job.description, job.progress.ratio() * 100.0, job.progress.describe())
+                    })
+                    .collect();
+                render_list_view(f, top_chunk, " Jobs (c: cancel, Esc: close) ", &jobs, &mut app_state.jobs_state, &app_state.config.theme);
+            } else if app_state.bookmarks_view {
+                let entries: Vec<String> = app_state.bookmarks.iter()
+                    .map(|(letter, path)| format!("{}  {}", letter, path.display()))
+                    .collect();
+                render_list_view(f, top_chunk, " Bookmarks (Enter: jump, d: delete, Esc: close) ", &entries, &mut app_state.bookmarks_state, &app_state.config.theme);
+            } else if app_state.trash_view {
+                let entries: Vec<String> = app_state.trash_entries.iter()
+                    .map(|entry| format!("{}  {}", entry.deletion_date, entry.original_path.display()))
+                    .collect();
+                render_list_view(f, top_chunk, " Trash (r: restore, E: empty, Esc: close) ", &entries, &mut app_state.trash_state, &app_state.config.theme);
+            } else if let Some(panel) = app_state.other_panel.as_ref() {
+                let panel_chunks = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+                    .split(top_chunk);
+                let (active_chunk, other_chunk) = if app_state.left_panel_active {
+                    (panel_chunks[0], panel_chunks[1])
+                } else {
+                    (panel_chunks[1], panel_chunks[0])
+                };
+
+                // Only the active panel shows its cursor
+                let mut other_state = panel.middle_state.clone();
+                other_state.select(None);
+                render_pane(f, active_chunk, &current_dir, &files, &mut middle_state, PaneType::Middle, &app_state.marked_files, &app_state.config.theme);
+                render_pane(f, other_chunk, &panel.current_dir, &other_files, &mut other_state, PaneType::Middle, &app_state.marked_files, &app_state.config.theme);
+            } else {
+                let ratios = app_state.config.pane_ratios;
+                let total: u32 = ratios.iter().sum();
+                let horizontal_chunks = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints(
+                        [
+                            Constraint::Ratio(ratios[0], total),
+                            Constraint::Ratio(ratios[1], total),
+                            Constraint::Ratio(ratios[2], total),
+                        ]
+                        .as_ref(),
+                    )
+                    .split(top_chunk);
+
+                let parent_dir = current_dir.parent().unwrap_or(&current_dir);
+                render_pane(f, horizontal_chunks[0], parent_dir, &parents, &mut left_state, PaneType::Left, &app_state.marked_files, &app_state.config.theme);
+                render_pane(f, horizontal_chunks[1], &current_dir, &files, &mut middle_state, PaneType::Middle, &app_state.marked_files, &app_state.config.theme);
+                render_pane(f, horizontal_chunks[2], &selected_dir, &children, &mut right_state, PaneType::Right, &app_state.marked_files, &app_state.config.theme);
+            }
 
             // Render the small horizontal pane for displaying text
             let text_to_display = match (&app_state.prompt_message, &app_state.renaming_buffer, &app_state.creation_buffer, &app_state.permissions_buffer) {
@@ -84,16 +187,36 @@ fn main() {
                 (Some(prompt), None, Some(buffer), None) => format!("{}{}", prompt, buffer),
                 (Some(prompt), None, None, Some(buffer)) => format!("{}{}", prompt, buffer),
                 (Some(prompt), None, None, None) => prompt.clone(),
+                (None, ..) => middle_state.selected()
+                    .and_then(|index| files.get(index).map(|file_info| (index, file_info)))
+                    .filter(|(_, file_info)| file_info.perms.is_some())
+                    .map(|(index, file_info)| status_line(file_info, index + 1, files.len()))
+                    .unwrap_or_default(),
                 _ => String::new(),
             };
 
             let text_paragraph = Paragraph::new(text_to_display);
-            f.render_widget(text_paragraph, vertical_chunks[1]);
+            if app_state.jobs.is_busy() {
+                let bottom_chunks = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+                    .split(vertical_chunks[1]);
+                f.render_widget(text_paragraph, bottom_chunks[0]);
+                render_job_progress(f, bottom_chunks[1], app_state.jobs.jobs(), &app_state.config.theme);
+            } else {
+                f.render_widget(text_paragraph, vertical_chunks[1]);
+            }
         }).unwrap();
 
-        // Handle input
-        if handle_input(&mut current_dir, &mut middle_state, &mut left_state, &files, &mut scroll_position, &max_scroll,&mut app_state) {
-            break;
+        match wait_for_event(&app_state) {
+            LoopEvent::Key(key_event) => {
+                if handle_input(key_event, &mut current_dir, &mut middle_state, &files, &mut scroll_position, &max_scroll, &mut app_state) {
+                    break;
+                }
+            }
+            // The terminal itself is resized on the next draw
+            LoopEvent::Resize(rows) => app_state.resize(rows),
+            LoopEvent::Tick => {}
         }
     }
 
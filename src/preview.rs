@@ -1,7 +1,20 @@
+use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::io::Read;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
 use std::fs::File;
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+
+const HEX_BYTES_PER_LINE: u64 = 16;
+const LINES_PER_CHECKPOINT: usize = 64;
+// How far past the visible lines each preview indexes, so the scroll range of a huge file
+// fills in over a few frames without stalling any of them
+const INDEX_BYTES_PER_CALL: u64 = 8 * 1024 * 1024;
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+// Longer lines are cut off; they couldn't be shown in full anyway
+const MAX_LINE_BYTES: usize = 4096;
+const MAX_CACHED_INDEXES: usize = 16;
 
 fn is_text_file(path: &Path) -> bool {
     if let Ok(mut file) = fs::File::open(path) {
@@ -16,24 +29,214 @@ fn is_text_file(path: &Path) -> bool {
 
 /// Retrieve a truncated content of the file based on the scroll position.
 fn get_truncated_content(contents: &str, scroll_position: usize, max_lines: usize) -> (String, usize) {
-    let lines: Vec<_> = contents.lines().collect();
-    let start_index = scroll_position.min(lines.len().saturating_sub(max_lines));
-    let truncated_contents = lines[start_index..start_index + max_lines.min(lines.len() - start_index)].join("\n");
-
-    let total_lines = lines.len();
-    let max_scroll_position = if total_lines > max_lines { total_lines - max_lines } else { 0 };
+    let total_lines = contents.lines().count();
+    let max_scroll_position = total_lines.saturating_sub(max_lines);
+    let start_index = scroll_position.min(max_scroll_position);
+    let truncated_contents: Vec<&str> = contents.lines().skip(start_index).take(max_lines).collect();
 
-    (truncated_contents, max_scroll_position)
+    (truncated_contents.join("\n"), max_scroll_position)
 }
 
+#[allow(clippy::ptr_arg)]
 pub fn get_file_preview(path: &PathBuf, scroll_position: usize, max_lines: usize) -> Result<(String, usize), Box<dyn std::error::Error>> {
     if is_text_file(path) {
-        let mut file = File::open(path)?;
-        let mut contents = String::new();
-        file.read_to_string(&mut contents)?;
-
-        Ok(get_truncated_content(&contents, scroll_position, max_lines))
+        get_text_window(path, scroll_position, max_lines)
     } else {
-        Ok(("Can't preview file".to_string(), 0))
+        get_hex_dump(path, scroll_position, max_lines)
+    }
+}
+
+/// Where lines start in a text file, filled in a little more on every preview. Only every
+/// `LINES_PER_CHECKPOINT`th offset is kept, so even a file with a billion lines has a small index.
+struct LineIndex {
+    modified: Option<SystemTime>,
+    len: u64,
+    checkpoints: Vec<u64>,
+    // Lines whose start has been found so far, and how much of the file was scanned to find them
+    lines: usize,
+    scanned: u64,
+}
+
+impl LineIndex {
+    fn new(modified: Option<SystemTime>, len: u64) -> Self {
+        LineIndex {
+            modified,
+            len,
+            // An empty file has no lines, anything else has at least the one at the start
+            checkpoints: if len > 0 { vec![0] } else { Vec::new() },
+            lines: usize::from(len > 0),
+            scanned: 0,
+        }
+    }
+
+    fn is_current(&self, metadata: &fs::Metadata) -> bool {
+        self.modified == metadata.modified().ok() && self.len == metadata.len()
+    }
+
+    /// Scan until `wanted_lines` lines are known, and then on for another `INDEX_BYTES_PER_CALL`.
+    fn extend(&mut self, file: &mut File, wanted_lines: usize) -> io::Result<()> {
+        let budget_end = self.scanned + INDEX_BYTES_PER_CALL;
+        file.seek(SeekFrom::Start(self.scanned))?;
+
+        let mut buffer = vec![0; READ_CHUNK_SIZE];
+        while self.scanned < self.len && (self.lines < wanted_lines || self.scanned < budget_end) {
+            let read = file.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            for (position, _) in buffer[..read].iter().enumerate().filter(|(_, &byte)| byte == b'\n') {
+                // A newline only starts another line if something follows it
+                let line_start = self.scanned + position as u64 + 1;
+                if line_start < self.len {
+                    if self.lines.is_multiple_of(LINES_PER_CHECKPOINT) {
+                        self.checkpoints.push(line_start);
+                    }
+                    self.lines += 1;
+                }
+            }
+            self.scanned += read as u64;
+        }
+        Ok(())
+    }
+
+    /// Read `count` lines from `start_line` on, seeking to the closest checkpoint before it.
+    fn read_lines(&self, file: &mut File, start_line: usize, count: usize) -> io::Result<Vec<String>> {
+        let Some(&checkpoint) = self.checkpoints.get(start_line / LINES_PER_CHECKPOINT) else {
+            return Ok(Vec::new());
+        };
+        file.seek(SeekFrom::Start(checkpoint))?;
+        let mut reader = BufReader::new(file);
+
+        for _ in 0..start_line % LINES_PER_CHECKPOINT {
+            read_line_capped(&mut reader, 0)?;
+        }
+
+        let mut lines = Vec::new();
+        while lines.len() < count {
+            match read_line_capped(&mut reader, MAX_LINE_BYTES)? {
+                Some(line) => lines.push(line),
+                None => break,
+            }
+        }
+        Ok(lines)
+    }
+}
+
+// Read up to the next newline while keeping at most `cap` bytes of it, so that a file that is
+// one enormous line doesn't end up in memory. `None` at the end of the file.
+fn read_line_capped(reader: &mut impl BufRead, cap: usize) -> io::Result<Option<String>> {
+    let mut line = Vec::new();
+    let mut found_any = false;
+
+    loop {
+        let buffer = reader.fill_buf()?;
+        if buffer.is_empty() {
+            break;
+        }
+        found_any = true;
+
+        let newline = buffer.iter().position(|&byte| byte == b'\n');
+        let end = newline.unwrap_or(buffer.len());
+        let room = cap.saturating_sub(line.len());
+        line.extend_from_slice(&buffer[..end.min(room)]);
+
+        reader.consume(newline.map_or(end, |position| position + 1));
+        if newline.is_some() {
+            break;
+        }
     }
+
+    if !found_any {
+        return Ok(None);
+    }
+    if line.last() == Some(&b'\r') {
+        line.pop();
+    }
+    Ok(Some(String::from_utf8_lossy(&line).into_owned()))
+}
+
+/// The visible lines of a text file. The line index is kept per path and reused while the file's
+/// modification time and size stay the same, so scrolling only reads the lines it shows.
+fn get_text_window(path: &Path, scroll_position: usize, max_lines: usize) -> Result<(String, usize), Box<dyn std::error::Error>> {
+    static INDEXES: OnceLock<Mutex<HashMap<PathBuf, LineIndex>>> = OnceLock::new();
+
+    let mut file = File::open(path)?;
+    let metadata = file.metadata()?;
+    let mut indexes = INDEXES.get_or_init(Default::default).lock().unwrap();
+
+    if !indexes.get(path).is_some_and(|index| index.is_current(&metadata)) {
+        if indexes.len() >= MAX_CACHED_INDEXES {
+            indexes.clear();
+        }
+        indexes.insert(path.to_path_buf(), LineIndex::new(metadata.modified().ok(), metadata.len()));
+    }
+    let index = indexes.get_mut(path).unwrap();
+
+    index.extend(&mut file, scroll_position + max_lines)?;
+    // Until the whole file has been scanned this only covers the part that has been
+    let max_scroll_position = index.lines.saturating_sub(max_lines);
+    let start_line = scroll_position.min(max_scroll_position);
+    let lines = index.read_lines(&mut file, start_line, max_lines)?;
+
+    Ok((lines.join("\n"), max_scroll_position))
+}
+
+/// A hex and ASCII dump of the visible lines. Only those bytes are read, by seeking to their offset,
+/// so scrolling through a large binary costs the same as a small one.
+fn get_hex_dump(path: &Path, scroll_position: usize, max_lines: usize) -> Result<(String, usize), Box<dyn std::error::Error>> {
+    let mut file = File::open(path)?;
+    let total_lines = file.metadata()?.len().div_ceil(HEX_BYTES_PER_LINE);
+    let max_scroll_position = total_lines.saturating_sub(max_lines as u64);
+    let start_line = (scroll_position as u64).min(max_scroll_position);
+
+    let start_offset = start_line * HEX_BYTES_PER_LINE;
+    file.seek(SeekFrom::Start(start_offset))?;
+    let mut buffer = Vec::new();
+    file.take(max_lines as u64 * HEX_BYTES_PER_LINE).read_to_end(&mut buffer)?;
+
+    Ok((format_hex_lines(&buffer, start_offset), max_scroll_position as usize))
+}
+
+/// Preview contents that are already in memory, such as a file read out of an archive.
+pub fn get_bytes_preview(contents: &[u8], scroll_position: usize, max_lines: usize) -> (String, usize) {
+    if !contents[..contents.len().min(512)].contains(&0) {
+        return get_truncated_content(&String::from_utf8_lossy(contents), scroll_position, max_lines);
+    }
+
+    let total_lines = (contents.len() as u64).div_ceil(HEX_BYTES_PER_LINE);
+    let max_scroll_position = total_lines.saturating_sub(max_lines as u64);
+    let start_offset = (scroll_position as u64).min(max_scroll_position) * HEX_BYTES_PER_LINE;
+    let end_offset = (start_offset + max_lines as u64 * HEX_BYTES_PER_LINE).min(contents.len() as u64);
+
+    (format_hex_lines(&contents[start_offset as usize..end_offset as usize], start_offset), max_scroll_position as usize)
+}
+
+fn format_hex_lines(bytes: &[u8], start_offset: u64) -> String {
+    let lines: Vec<String> = bytes
+        .chunks(HEX_BYTES_PER_LINE as usize)
+        .zip((start_offset..).step_by(HEX_BYTES_PER_LINE as usize))
+        .map(|(bytes, offset)| format_hex_line(offset, bytes))
+        .collect();
+    lines.join("\n")
+}
+
+// One line in the style of `hexdump -C`: offset, two groups of eight bytes and the printable characters
+fn format_hex_line(offset: u64, bytes: &[u8]) -> String {
+    let mut hex = String::new();
+    for index in 0..HEX_BYTES_PER_LINE as usize {
+        if index == 8 {
+            hex.push(' ');
+        }
+        match bytes.get(index) {
+            Some(byte) => hex.push_str(&format!("{:02x} ", byte)),
+            None => hex.push_str("   "),
+        }
+    }
+
+    let ascii: String = bytes
+        .iter()
+        .map(|&byte| if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' })
+        .collect();
+
+    format!("{:08x}  {} |{}|", offset, hex, ascii)
 }
@@ -0,0 +1,174 @@
+use std::cmp::Ordering;
+use std::ffi::OsStr;
+use std::path::Path;
+
+use crate::fs_utils::FileInfo;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortMode {
+    Name,
+    Size,
+    Modified,
+    Changed,
+    Extension,
+    Type,
+}
+
+impl SortMode {
+    pub fn next(self) -> SortMode {
+        match self {
+            SortMode::Name      => SortMode::Size,
+            SortMode::Size      => SortMode::Modified,
+            SortMode::Modified  => SortMode::Changed,
+            SortMode::Changed   => SortMode::Extension,
+            SortMode::Extension => SortMode::Type,
+            SortMode::Type      => SortMode::Name,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortMode::Name      => "name",
+            SortMode::Size      => "size",
+            SortMode::Modified  => "modification time",
+            SortMode::Changed   => "change time",
+            SortMode::Extension => "extension",
+            SortMode::Type      => "type",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SortOptions {
+    pub mode: SortMode,
+    pub dirs_first: bool,
+    pub reverse: bool,
+}
+
+impl Default for SortOptions {
+    fn default() -> Self {
+        SortOptions {
+            mode: SortMode::Name,
+            dirs_first: true,
+            reverse: false,
+        }
+    }
+}
+
+impl SortOptions {
+    pub fn describe(&self) -> String {
+        let mut flags = Vec::new();
+        if self.reverse {
+            flags.push("reversed");
+        }
+        if self.dirs_first {
+            flags.push("directories first");
+        }
+
+        if flags.is_empty() {
+            format!(" Sort: {}", self.mode.label())
+        } else {
+            format!(" Sort: {} ({})", self.mode.label(), flags.join(", "))
+        }
+    }
+}
+
+/// Sort a directory listing in place. Size and time modes put the largest/newest entries first,
+/// `reverse` flips that order but never moves directories out of their group when `dirs_first` is set.
+pub fn sort_files(files: &mut [FileInfo], options: &SortOptions) {
+    files.sort_by(|a, b| {
+        if options.dirs_first && a.is_dir != b.is_dir {
+            return b.is_dir.cmp(&a.is_dir);
+        }
+
+        let ordering = compare_by_mode(a, b, options.mode)
+            .then_with(|| natural_cmp(&a.display_name(), &b.display_name()))
+            // Names that only differ in their invalid UTF-8 look the same once displayed
+            .then_with(|| a.name.cmp(&b.name));
+        if options.reverse {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    });
+}
+
+fn compare_by_mode(a: &FileInfo, b: &FileInfo, mode: SortMode) -> Ordering {
+    match mode {
+        SortMode::Name      => Ordering::Equal,
+        SortMode::Size      => b.size.cmp(&a.size),
+        SortMode::Modified  => b.modified.cmp(&a.modified),
+        SortMode::Changed   => b.changed.cmp(&a.changed),
+        SortMode::Extension => extension_of(&a.name).cmp(&extension_of(&b.name)),
+        SortMode::Type      => type_rank(a).cmp(&type_rank(b)),
+    }
+}
+
+fn extension_of(name: &OsStr) -> String {
+    Path::new(name)
+        .extension()
+        .map_or(String::new(), |ext| ext.to_string_lossy().to_lowercase())
+}
+
+fn type_rank(file_info: &FileInfo) -> u8 {
+    if file_info.is_dir {
+        0
+    } else if file_info.is_exec {
+        1
+    } else {
+        2
+    }
+}
+
+/// Compare two names the way a human would: case-insensitively, with runs of digits compared by
+/// their numeric value so that "file2" sorts before "file10".
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a_chars = a.chars().peekable();
+    let mut b_chars = b.chars().peekable();
+
+    loop {
+        match (a_chars.peek().copied(), b_chars.peek().copied()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let a_number = take_digits(&mut a_chars);
+                let b_number = take_digits(&mut b_chars);
+                let ordering = compare_digit_runs(&a_number, &b_number);
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let ordering = x.to_lowercase().cmp(y.to_lowercase());
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                a_chars.next();
+                b_chars.next();
+            }
+        }
+    }
+}
+
+fn take_digits(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    let mut digits = String::new();
+    while let Some(c) = chars.peek().copied() {
+        if !c.is_ascii_digit() {
+            break;
+        }
+        digits.push(c);
+        chars.next();
+    }
+    digits
+}
+
+// Digit runs can be longer than any integer type, so compare them as strings without leading zeros
+fn compare_digit_runs(a: &str, b: &str) -> Ordering {
+    let a_trimmed = a.trim_start_matches('0');
+    let b_trimmed = b.trim_start_matches('0');
+    a_trimmed
+        .len()
+        .cmp(&b_trimmed.len())
+        .then_with(|| a_trimmed.cmp(b_trimmed))
+}
@@ -1,9 +1,102 @@
+use std::collections::{BTreeSet, VecDeque};
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tui::widgets::ListState;
+
+use crate::sort::SortOptions;
+use crate::trash::TrashEntry;
+use crate::journal::Journal;
+use crate::jobs::JobManager;
+use crate::fs_utils::{ConflictPolicy, FileInfo};
+use crate::config::{Config, KeyPress};
+use crate::filter::FileFilter;
+use crate::bookmarks::Bookmarks;
+use crate::history::History;
+use crate::tabs::{Tab, Tabs};
+
+/// A paste waiting for the user to decide what to do about destinations that already exist.
+pub struct PendingPaste {
+    pub was_cut: bool,
+    pub items: Vec<(PathBuf, PathBuf, ConflictPolicy)>,
+    pub conflicts: VecDeque<(PathBuf, PathBuf)>,
+    pub apply_to_all: bool,
+    pub remembered_policy: Option<ConflictPolicy>,
+    // Directories left out for being pasted into themselves, reported once the paste starts
+    pub into_itself: Vec<PathBuf>,
+}
+
+/// The files marked for the next operation. Marks are kept by full path, so they stay in place
+/// while moving between directories.
+#[derive(Default)]
+pub struct Marks(BTreeSet<PathBuf>);
+
+impl Marks {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        self.0.contains(path)
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    /// Mark `path`, or unmark it if it already was.
+    pub fn toggle(&mut self, path: PathBuf) {
+        if !self.0.remove(&path) {
+            self.0.insert(path);
+        }
+    }
+
+    pub fn mark_all(&mut self, dir: &Path, files: &[FileInfo]) {
+        self.0.extend(files.iter().map(|file_info| dir.join(&file_info.name)));
+    }
+
+    pub fn invert(&mut self, dir: &Path, files: &[FileInfo]) {
+        for file_info in files {
+            self.toggle(dir.join(&file_info.name));
+        }
+    }
+
+    /// What a file operation applies to: the marked files if there are any, otherwise the selected
+    /// entry as long as it still exists. With `follow_symlinks` links are swapped for their targets.
+    pub fn targets(&self, dir: &Path, selected: Option<usize>, files: &[FileInfo], follow_symlinks: bool) -> Vec<PathBuf> {
+        let targets: Vec<PathBuf> = if !self.0.is_empty() {
+            self.0.iter().cloned().collect()
+        } else {
+            selected
+                .and_then(|index| files.get(index))
+                .map(|file_info| dir.join(&file_info.name))
+                .filter(|path| path.symlink_metadata().is_ok())
+                .into_iter()
+                .collect()
+        };
+
+        if follow_symlinks {
+            targets.into_iter().map(|path| fs::canonicalize(&path).unwrap_or(path)).collect()
+        } else {
+            targets
+        }
+    }
+}
+
+/// What the next letter typed in normal mode is for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BookmarkCommand {
+    Set,
+    Jump,
+}
+
 pub struct AppState {
-    pub last_key_pressed: Option<char>,
-    pub last_modifier: Option<crossterm::event::KeyModifiers>,
+    pub config: Config,
+    pub pending_keys: Vec<KeyPress>,
     pub was_cut: bool,
     pub terminal_height: usize,
     pub delete_mode: bool,
+    pub permanent_delete: bool,
     pub rename_mode: bool,
     pub renaming_buffer: Option<String>,
     pub prompt_message: Option<String>,
@@ -12,35 +105,104 @@ pub struct AppState {
     pub last_search_index: Option<usize>,
     pub is_creating_file: bool,
     pub is_creating_directory: bool,
-    pub selected_file_for_copy: Option<std::path::PathBuf>,
+    pub is_creating_archive: bool,
+    pub selected_files_for_copy: Vec<PathBuf>,
+    pub marked_files: Marks,
     pub creation_buffer: Option<String>,
     pub is_changing_permissions: bool,
     pub permissions_buffer: Option<String>,
+    pub sort_options: SortOptions,
+    pub file_filter: FileFilter,
+    pub follow_symlinks: bool,
+    pub select_on_refresh: Option<OsString>,
+    pub trash_view: bool,
+    pub trash_entries: Vec<TrashEntry>,
+    pub trash_state: ListState,
+    pub is_emptying_trash: bool,
+    pub journal: Journal,
+    pub jobs: JobManager,
+    pub jobs_view: bool,
+    pub jobs_state: ListState,
+    pub pending_paste: Option<PendingPaste>,
+    pub bookmarks: Bookmarks,
+    pub pending_bookmark: Option<BookmarkCommand>,
+    pub bookmarks_view: bool,
+    pub bookmarks_state: ListState,
+    pub history: History,
+    pub tabs: Tabs,
+    // The panel that isn't active in the dual-pane layout, which is off while this is `None`
+    pub other_panel: Option<Tab>,
+    pub left_panel_active: bool,
 }
 
 impl AppState {
+    #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
-        // Calculate the half screen size
         let terminal_size = crossterm::terminal::size().unwrap();
 
+        let (config, config_errors) = Config::load();
+        let mut prompt_message = config_errors.first().map(|error| match config_errors.len() {
+            1 => format!(" Config error: {}", error),
+            count => format!(" Config error: {} (and {} more)", error, count - 1),
+        });
+
+        let bookmarks = Bookmarks::home().unwrap_or_else(|e| {
+            prompt_message.get_or_insert_with(|| format!(" Failed to read the bookmarks: {}", e));
+            Bookmarks::default()
+        });
+
         AppState {
-            last_key_pressed: None,
-            last_modifier: None,
+            sort_options: config.sort_options,
+            file_filter: config.filter.clone(),
+            follow_symlinks: config.follow_symlinks,
+            config,
+            pending_keys: Vec::new(),
             was_cut: false,
-            terminal_height : (terminal_size.1 as usize - 4) * 90 / 100,
+            terminal_height: pane_height(terminal_size.1),
             delete_mode: false,
+            permanent_delete: false,
             rename_mode: false,
             renaming_buffer: None,
-            prompt_message: None,
+            prompt_message,
             search_pattern: None,
             search_mode: false,
             last_search_index: None,
             is_creating_file: false,
             is_creating_directory: false,
-            selected_file_for_copy: None,
+            is_creating_archive: false,
+            selected_files_for_copy: Vec::new(),
+            marked_files: Marks::default(),
             creation_buffer: None,
             is_changing_permissions: false,
             permissions_buffer: None,
+            select_on_refresh: None,
+            trash_view: false,
+            trash_entries: Vec::new(),
+            trash_state: ListState::default(),
+            is_emptying_trash: false,
+            journal: Journal::default(),
+            jobs: JobManager::new(),
+            jobs_view: false,
+            jobs_state: ListState::default(),
+            pending_paste: None,
+            bookmarks,
+            pending_bookmark: None,
+            bookmarks_view: false,
+            bookmarks_state: ListState::default(),
+            history: History::default(),
+            tabs: Tabs::default(),
+            other_panel: None,
+            left_panel_active: true,
         }
     }
+
+    /// Recompute what depends on the size of the terminal after it was resized to `rows` lines.
+    pub fn resize(&mut self, rows: u16) {
+        self.terminal_height = pane_height(rows);
+    }
+}
+
+// The number of lines inside the panes, used for half-page moves and sizing the preview
+fn pane_height(rows: u16) -> usize {
+    (rows as usize).saturating_sub(4) * 90 / 100
 }
\ No newline at end of file
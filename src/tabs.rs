@@ -0,0 +1,93 @@
+use std::path::{Path, PathBuf};
+use tui::widgets::ListState;
+
+use crate::filter::FileFilter;
+use crate::history::History;
+use crate::sort::SortOptions;
+
+/// A tab in the background, or the inactive panel of the dual-pane layout. The tab on screen keeps the
+/// same state in `main`'s loop variables and the fields of `AppState`, and is swapped with one of these.
+#[derive(Clone, Default)]
+pub struct Tab {
+    pub current_dir: PathBuf,
+    pub middle_state: ListState,
+    pub scroll_position: usize,
+    pub sort_options: SortOptions,
+    pub file_filter: FileFilter,
+    pub history: History,
+    // The tab's dual-pane layout while it is in the background; a panel never has one of its own
+    pub other_panel: Option<Box<Tab>>,
+    pub left_panel_active: bool,
+}
+
+/// The tabs in the background and where the tab on screen sits between them. The tab on screen
+/// isn't kept here; it is handed over whenever it goes into the background.
+#[derive(Default)]
+pub struct Tabs {
+    // In order, with the tab on screen sitting just before `background[active]`
+    background: Vec<Tab>,
+    active: usize,
+}
+
+impl Tabs {
+    /// The number of tabs, including the one on screen.
+    pub fn count(&self) -> usize {
+        self.background.len() + 1
+    }
+
+    /// The position of the tab on screen among all tabs.
+    pub fn active(&self) -> usize {
+        self.active
+    }
+
+    /// Put `current` in the background, just before the new tab that takes its place on screen.
+    pub fn open(&mut self, current: Tab) {
+        self.background.insert(self.active, current);
+        self.active += 1;
+    }
+
+    /// Drop the tab on screen and take out the one that replaces it: the tab after it, or the one
+    /// before if it was the last. `None` when it is the only tab.
+    pub fn close(&mut self) -> Option<Tab> {
+        if self.background.is_empty() {
+            return None;
+        }
+        self.active = self.active.min(self.background.len() - 1);
+        Some(self.background.remove(self.active))
+    }
+
+    /// Put `current` in the background and take out the tab at `index`, counting all tabs in order.
+    /// `current` is handed back if it already is the tab at `index`, or if there is no such tab.
+    pub fn switch_to(&mut self, index: usize, current: Tab) -> Tab {
+        if index == self.active || index >= self.count() {
+            return current;
+        }
+        self.background.insert(self.active, current);
+        self.active = index;
+        self.background.remove(index)
+    }
+
+    /// The position of the tab after the one on screen, wrapping around to the first.
+    pub fn next(&self) -> usize {
+        (self.active + 1) % self.count()
+    }
+
+    /// The position of the tab before the one on screen, wrapping around to the last.
+    pub fn previous(&self) -> usize {
+        (self.active + self.count() - 1) % self.count()
+    }
+
+    /// The labels of all tabs in order, given that the one on screen shows `current_dir`.
+    pub fn labels(&self, current_dir: &Path) -> Vec<String> {
+        let (before, after) = self.background.split_at(self.active);
+        before.iter().map(|tab| tab.current_dir.as_path())
+            .chain(std::iter::once(current_dir))
+            .chain(after.iter().map(|tab| tab.current_dir.as_path()))
+            .enumerate()
+            .map(|(index, dir)| {
+                let name = dir.file_name().map(|name| name.to_string_lossy()).unwrap_or_else(|| dir.to_string_lossy());
+                format!(" {}:{} ", index + 1, name)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,330 @@
+use std::env;
+use std::ffi::OsString;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::fs_utils::{self, data_home, decode_path, encode_path, make_unique_path};
+
+const TRASH_INFO_EXTENSION: &str = "trashinfo";
+const DELETION_DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// A single trashed item, described by its `.trashinfo` file.
+#[derive(Clone, Debug)]
+pub struct TrashEntry {
+    pub name: OsString,
+    pub original_path: PathBuf,
+    pub deletion_date: String,
+    /// The trash directory holding the entry, i.e. what `Trash::new` takes.
+    pub trash_dir: PathBuf,
+}
+
+/// A trash directory as described by the freedesktop.org Trash specification.
+pub struct Trash {
+    pub root: PathBuf,
+    pub files_dir: PathBuf,
+    pub info_dir: PathBuf,
+    // Where relative paths in the info files start from, for the trashes at the top of a mount
+    topdir: Option<PathBuf>,
+}
+
+impl Trash {
+    pub fn new(root: &Path) -> Self {
+        Trash {
+            root: root.to_path_buf(),
+            files_dir: root.join("files"),
+            info_dir: root.join("info"),
+            topdir: None,
+        }
+    }
+
+    /// The trash in `$XDG_DATA_HOME/Trash`, falling back to `~/.local/share/Trash`.
+    pub fn home() -> io::Result<Self> {
+        Ok(Trash::new(&data_home()?.join("Trash")))
+    }
+
+    /// The trash `path` should go to: the home trash if it is on the same file system, otherwise
+    /// the trash at the top of its own file system, so that trashing is always a rename rather
+    /// than a copy. Falls back to the home trash when that one can't be set up.
+    pub fn for_path(path: &Path) -> io::Result<Self> {
+        let home = Trash::home()?;
+        let topdir = std::path::absolute(path).ok().and_then(|path| other_mount_point(&path, &home.root));
+        Ok(topdir.and_then(|topdir| Trash::in_topdir(&topdir).ok()).unwrap_or(home))
+    }
+
+    /// The trash at the top of a mounted file system: `$topdir/.Trash/$uid` when the administrator
+    /// has set up a shared `.Trash` with the sticky bit, otherwise `$topdir/.Trash-$uid`.
+    pub fn in_topdir(topdir: &Path) -> io::Result<Self> {
+        let mut error = None;
+        for root in topdir_trash_dirs(topdir) {
+            match create_private_dir(&root) {
+                Ok(()) => return Ok(Trash { topdir: Some(topdir.to_path_buf()), ..Trash::new(&root) }),
+                Err(e) => error = Some(e),
+            }
+        }
+        Err(error.unwrap_or_else(|| io::Error::new(io::ErrorKind::Unsupported, "No trash on this file system")))
+    }
+
+    /// The home trash followed by the existing trashes at the top of the other mounted file systems.
+    pub fn all() -> io::Result<Vec<Self>> {
+        let mut trashes = vec![Trash::home()?];
+        for topdir in mount_points() {
+            trashes.extend(
+                topdir_trash_dirs(&topdir)
+                    .into_iter()
+                    .filter(|root| fs::symlink_metadata(root.join("info")).is_ok_and(|metadata| metadata.is_dir()))
+                    .map(|root| Trash { topdir: Some(topdir.clone()), ..Trash::new(&root) }),
+            );
+        }
+        Ok(trashes)
+    }
+
+    /// Move `path` into the trash, writing its `.trashinfo` file first as the spec requires.
+    pub fn trash(&self, path: &Path) -> io::Result<TrashEntry> {
+        fs::create_dir_all(&self.files_dir)?;
+        fs::create_dir_all(&self.info_dir)?;
+
+        let original_path = if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            env::current_dir()?.join(path)
+        };
+        let file_name = original_path
+            .file_name()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Cannot trash a path without a file name"))?;
+        let deletion_date = chrono::Local::now().format(DELETION_DATE_FORMAT).to_string();
+
+        let (name, info_path) = self.create_info_file(file_name, &original_path, &deletion_date)?;
+        if let Err(e) = fs_utils::move_file(&original_path, &self.files_dir.join(&name)) {
+            let _ = fs::remove_file(&info_path);
+            return Err(e);
+        }
+
+        Ok(TrashEntry {
+            name,
+            original_path,
+            deletion_date,
+            trash_dir: self.root.clone(),
+        })
+    }
+
+    // Reserve a unique name by atomically creating its info file, so concurrent trashers never collide
+    fn create_info_file(&self, file_name: &std::ffi::OsStr, original_path: &Path, deletion_date: &str) -> io::Result<(OsString, PathBuf)> {
+        let contents = format!(
+            "[Trash Info]\nPath={}\nDeletionDate={}\n",
+            encode_path(original_path),
+            deletion_date
+        );
+
+        let mut counter = 1;
+        let mut name = file_name.to_os_string();
+        loop {
+            let info_path = self.info_path(&name);
+            if self.files_dir.join(&name).symlink_metadata().is_err() {
+                match OpenOptions::new().write(true).create_new(true).open(&info_path) {
+                    Ok(mut info_file) => {
+                        info_file.write_all(contents.as_bytes())?;
+                        return Ok((name, info_path));
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+                    Err(e) => return Err(e),
+                }
+            }
+
+            name = file_name.to_os_string();
+            name.push(format!(".{}", counter));
+            counter += 1;
+        }
+    }
+
+    fn info_path(&self, name: &std::ffi::OsStr) -> PathBuf {
+        let mut info_name = name.to_os_string();
+        info_name.push(".");
+        info_name.push(TRASH_INFO_EXTENSION);
+        self.info_dir.join(info_name)
+    }
+
+    /// All entries with a readable `.trashinfo` file, most recently deleted first.
+    pub fn list(&self) -> io::Result<Vec<TrashEntry>> {
+        let entries = match fs::read_dir(&self.info_dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        let mut trash_entries: Vec<TrashEntry> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == TRASH_INFO_EXTENSION))
+            .filter_map(|path| {
+                let name = path.file_stem()?.to_os_string();
+                let contents = fs::read_to_string(&path).ok()?;
+                let mut entry = parse_info_file(name, &contents, &self.root)?;
+                if let Some(topdir) = &self.topdir {
+                    entry.original_path = topdir.join(&entry.original_path);
+                }
+                Some(entry)
+            })
+            .collect();
+
+        trash_entries.sort_by(|a, b| b.deletion_date.cmp(&a.deletion_date));
+        Ok(trash_entries)
+    }
+
+    /// Move an entry back to where it came from, picking a unique name if that spot was taken since.
+    pub fn restore(&self, entry: &TrashEntry) -> io::Result<PathBuf> {
+        let destination = make_unique_path(entry.original_path.clone());
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs_utils::move_file(&self.files_dir.join(&entry.name), &destination)?;
+        fs::remove_file(self.info_path(&entry.name))?;
+        Ok(destination)
+    }
+
+    pub fn empty(&self) -> io::Result<()> {
+        for dir in [&self.files_dir, &self.info_dir] {
+            let entries = match fs::read_dir(dir) {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            };
+            for entry in entries {
+                fs_utils::delete(&entry?.path())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse_info_file(name: OsString, contents: &str, trash_dir: &Path) -> Option<TrashEntry> {
+    let mut lines = contents.lines().map(str::trim);
+    if lines.next()? != "[Trash Info]" {
+        return None;
+    }
+
+    let mut original_path = None;
+    let mut deletion_date = String::new();
+    for line in lines {
+        if let Some(value) = line.strip_prefix("Path=") {
+            original_path = Some(decode_path(value));
+        } else if let Some(value) = line.strip_prefix("DeletionDate=") {
+            deletion_date = value.to_string();
+        }
+    }
+
+    Some(TrashEntry {
+        name,
+        original_path: original_path?,
+        deletion_date,
+        trash_dir: trash_dir.to_path_buf(),
+    })
+}
+
+// The shared `.Trash/$uid` is only an option when `.Trash` is a real directory with the sticky bit,
+// so other users can't swap it for a symlink or delete what is inside
+#[cfg(unix)]
+fn topdir_trash_dirs(topdir: &Path) -> Vec<PathBuf> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let uid = unsafe { libc::getuid() };
+    let shared = topdir.join(".Trash");
+    let mut roots = Vec::new();
+    if fs::symlink_metadata(&shared).is_ok_and(|metadata| metadata.is_dir() && metadata.permissions().mode() & 0o1000 != 0) {
+        roots.push(shared.join(uid.to_string()));
+    }
+    roots.push(topdir.join(format!(".Trash-{}", uid)));
+    roots
+}
+
+#[cfg(windows)]
+fn topdir_trash_dirs(_topdir: &Path) -> Vec<PathBuf> {
+    Vec::new()
+}
+
+#[cfg(unix)]
+fn create_private_dir(root: &Path) -> io::Result<()> {
+    use std::os::unix::fs::DirBuilderExt;
+
+    match fs::DirBuilder::new().mode(0o700).create(root) {
+        Err(e) if e.kind() != io::ErrorKind::AlreadyExists => return Err(e),
+        _ => {}
+    }
+    if fs::symlink_metadata(root)?.is_symlink() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{} is a symlink", root.display())));
+    }
+    for dir in ["files", "info"] {
+        fs::DirBuilder::new().recursive(true).mode(0o700).create(root.join(dir))?;
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn create_private_dir(root: &Path) -> io::Result<()> {
+    fs::create_dir_all(root)
+}
+
+// The top of the file system `path` is on, or `None` when that is also where the home trash is.
+// The home trash may not exist yet, so its device is taken from its nearest existing ancestor.
+#[cfg(unix)]
+fn other_mount_point(path: &Path, home_trash: &Path) -> Option<PathBuf> {
+    use std::os::unix::fs::MetadataExt;
+
+    let device = |path: &Path| path.ancestors().find_map(|dir| fs::symlink_metadata(dir).ok()).map(|metadata| metadata.dev());
+    let mut topdir = path.parent()?;
+    let path_device = device(topdir)?;
+    if device(home_trash) == Some(path_device) {
+        return None;
+    }
+
+    while let Some(parent) = topdir.parent().filter(|parent| device(parent) == Some(path_device)) {
+        topdir = parent;
+    }
+    Some(topdir.to_path_buf())
+}
+
+#[cfg(windows)]
+fn other_mount_point(_path: &Path, _home_trash: &Path) -> Option<PathBuf> {
+    None
+}
+
+// Spaces and other special characters in the mount table are written as octal escapes like `\040`
+#[cfg(target_os = "linux")]
+fn mount_points() -> Vec<PathBuf> {
+    let Ok(mounts) = fs::read_to_string("/proc/self/mounts") else {
+        return Vec::new();
+    };
+
+    mounts
+        .lines()
+        .filter_map(|line| line.split(' ').nth(1))
+        .map(|mount_point| {
+            let bytes = mount_point.as_bytes();
+            let mut decoded = Vec::with_capacity(bytes.len());
+            let mut index = 0;
+            while index < bytes.len() {
+                let escaped = bytes.get(index + 1..index + 4)
+                    .filter(|_| bytes[index] == b'\\')
+                    .and_then(|octal| std::str::from_utf8(octal).ok())
+                    .and_then(|octal| u8::from_str_radix(octal, 8).ok());
+                match escaped {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        index += 4;
+                    }
+                    None => {
+                        decoded.push(bytes[index]);
+                        index += 1;
+                    }
+                }
+            }
+            fs_utils::path_from_bytes(decoded)
+        })
+        .collect()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn mount_points() -> Vec<PathBuf> {
+    Vec::new()
+}
@@ -1,20 +1,21 @@
 use tui::{
-    widgets::{Block, Borders, List, ListItem, ListState},
+    widgets::{Block, Borders, Gauge, List, ListItem, ListState, Paragraph},
     layout::Rect,
-    style::{Color, Style},
+    style::Style,
+    text::{Span, Spans, Text},
     Frame,
     backend::CrosstermBackend,
 };
 use std::io::Stdout;
+use std::path::Path;
 
 use super::fs_utils::{self, FileInfo};
+use super::jobs::JobStatus;
+use super::config::Theme;
+use super::highlight;
+use super::state::Marks;
 
-// Constants for repeated styles
-const DIR_COLOR: Color = Color::Blue;
-const EXEC_COLOR: Color = Color::Green;
-const FILE_COLOR: Color = Color::White;
-
-const SELECTED_BG_COLOR: Color = Color::Black;
+const MODIFIED_FORMAT: &str = "%Y-%m-%d %H:%M";
 
 #[derive(PartialEq)]
 pub enum PaneType {
@@ -23,13 +24,20 @@ pub enum PaneType {
     Right,
 }
 
-fn get_style_for_file(file_info: &FileInfo, is_selected: bool) -> Style {
-    let (fg, bg) = if file_info.is_dir {
-        (DIR_COLOR, SELECTED_BG_COLOR)
+fn get_style_for_file(file_info: &FileInfo, is_selected: bool, is_marked: bool, theme: &Theme) -> Style {
+    let bg = theme.selected_background;
+    let fg = if is_marked {
+        theme.marked
+    } else if file_info.is_broken_link {
+        theme.broken_symlink
+    } else if file_info.is_symlink {
+        theme.symlink
+    } else if file_info.is_dir {
+        theme.directory
     } else if file_info.is_exec {
-        (EXEC_COLOR, SELECTED_BG_COLOR)
+        theme.executable
     } else {
-        (FILE_COLOR, SELECTED_BG_COLOR)
+        theme.file
     };
 
     if is_selected {
@@ -39,25 +47,44 @@ fn get_style_for_file(file_info: &FileInfo, is_selected: bool) -> Style {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn render_pane(
     f: &mut Frame<CrosstermBackend<Stdout>>,
     chunk: Rect,
+    dir: &Path,
     items: &[FileInfo],
     state: &mut ListState,
     pane_type: PaneType,
+    marked_files: &Marks,
+    theme: &Theme,
 ) {
+    // A file preview is a single item holding the visible lines, which get coloured when the language is known
+    if pane_type == PaneType::Right && items.len() == 1 && dir.is_file() {
+        if let Some(syntax) = highlight::detect_syntax(dir) {
+            let preview = ListItem::new(Text::from(highlight::highlight(&items[0].display_name(), syntax)));
+            f.render_stateful_widget(List::new(vec![preview]).block(Block::default().borders(Borders::ALL)), chunk, state);
+            return;
+        }
+    }
+
     let list_items: Vec<ListItem> = items.iter().enumerate().map(|(index, file_info)| {
         let item_content = match pane_type {
             PaneType::Middle if file_info.perms.is_some() => {
-                let perms_str = fs_utils::get_permissions(&file_info.perms.as_ref().unwrap());
-                format!("{:<width$} {}", file_info.name, perms_str, width = chunk.width as usize - perms_str.len() - 4)
+                let perms_str = fs_utils::get_permissions(file_info.perms.as_ref().unwrap());
+                let name = match &file_info.symlink_target {
+                    Some(target) => format!("{} -> {}", file_info.display_name(), target.display()),
+                    None => file_info.display_name().into_owned(),
+                };
+                let width = (chunk.width as usize).saturating_sub(perms_str.len() + 4);
+                format!("{:<width$} {}", name, perms_str, width = width)
             }
-            _ => file_info.name.clone(),
+            _ => file_info.display_name().into_owned(),
         };
 
         let is_selected = Some(index) == state.selected();
+        let is_marked = !marked_files.is_empty() && marked_files.contains(&dir.join(&file_info.name));
 
-        let item_style = get_style_for_file(file_info, is_selected);
+        let item_style = get_style_for_file(file_info, is_selected, is_marked, theme);
         ListItem::new(item_content).style(item_style)
     }).collect();
 
@@ -67,3 +94,88 @@ pub fn render_pane(
 
     f.render_stateful_widget(files_list, chunk, state);
 }
+
+/// A full-width list used by the views that replace the file panes (e.g. the trash browser).
+pub fn render_list_view(
+    f: &mut Frame<CrosstermBackend<Stdout>>,
+    chunk: Rect,
+    title: &str,
+    items: &[String],
+    state: &mut ListState,
+    theme: &Theme,
+) {
+    let list_items: Vec<ListItem> = items.iter().map(|item| {
+        ListItem::new(item.as_str()).style(Style::default().fg(theme.file))
+    }).collect();
+
+    let list = List::new(list_items)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_style(Style::default().fg(theme.selected_background).bg(theme.file));
+
+    f.render_stateful_widget(list, chunk, state);
+}
+
+/// One line with a label per tab, the one on screen highlighted.
+pub fn render_tab_bar(
+    f: &mut Frame<CrosstermBackend<Stdout>>,
+    chunk: Rect,
+    labels: &[String],
+    active: usize,
+    theme: &Theme,
+) {
+    let spans: Vec<Span> = labels.iter().enumerate().map(|(index, label)| {
+        let style = if index == active {
+            Style::default().fg(theme.selected_background).bg(theme.directory)
+        } else {
+            Style::default().fg(theme.directory)
+        };
+        Span::styled(label.as_str(), style)
+    }).collect();
+
+    f.render_widget(Paragraph::new(Spans::from(spans)), chunk);
+}
+
+/// A progress bar for the running job, with a count of the ones still queued behind it.
+pub fn render_job_progress(
+    f: &mut Frame<CrosstermBackend<Stdout>>,
+    chunk: Rect,
+    jobs: &[JobStatus],
+    theme: &Theme,
+) {
+    if let Some(job) = jobs.iter().find(|job| job.started).or(jobs.first()) {
+        let queued = jobs.len() - 1;
+        let label = if queued > 0 {
+            format!("{}: {} (+{} queued)", job.description, job.progress.describe(), queued)
+        } else {
+            format!("{}: {}", job.description, job.progress.describe())
+        };
+
+        let gauge = Gauge::default()
+            .gauge_style(Style::default().fg(theme.progress).bg(theme.selected_background))
+            .ratio(job.progress.ratio())
+            .label(label);
+        f.render_widget(gauge, chunk);
+    }
+}
+
+/// A summary of the entry under the cursor: its position in the listing, permissions, owner, size,
+/// modification time and, for symlinks, where it points.
+pub fn status_line(file_info: &FileInfo, position: usize, total: usize) -> String {
+    let mut fields = vec![format!("{}/{}", position, total)];
+
+    if let Some(perms) = &file_info.perms {
+        fields.push(format!("{} ({})", fs_utils::get_permissions(perms), fs_utils::get_octal_permissions(perms)));
+    }
+    fields.extend(fs_utils::get_owner(file_info));
+    if !file_info.is_dir {
+        fields.push(fs_utils::format_size(file_info.size));
+    }
+    if let Some(modified) = file_info.modified {
+        fields.push(chrono::DateTime::<chrono::Local>::from(modified).format(MODIFIED_FORMAT).to_string());
+    }
+    if let Some(target) = &file_info.symlink_target {
+        fields.push(format!("-> {}", target.display()));
+    }
+
+    format!(" {}", fields.join("  "))
+}
@@ -0,0 +1,227 @@
+#[cfg(test)]
+mod tests {
+    use file_manager::archive::*;
+    use file_manager::filter::FileFilter;
+    use file_manager::fs_utils::{fetch_children, get_files_and_dirs};
+    use tempfile::tempdir;
+    use std::fs::{self, File};
+    use std::io::Write;
+    use std::path::{Path, PathBuf};
+
+    fn create_zip(path: &Path) {
+        let mut writer = zip::ZipWriter::new(File::create(path).unwrap());
+        let options = zip::write::FileOptions::default();
+        writer.add_directory("docs/", options).unwrap();
+        writer.start_file("docs/readme.txt", options).unwrap();
+        writer.write_all(b"first line\nsecond line\n").unwrap();
+        // No directory entry for "src", it has to be implied
+        writer.start_file("src/lib/mod.rs", options).unwrap();
+        writer.write_all(b"pub mod lib;\n").unwrap();
+        writer.start_file("top.bin", options).unwrap();
+        writer.write_all(&[0, 1, 2, 3]).unwrap();
+        writer.finish().unwrap();
+    }
+
+    fn create_tar_gz(path: &Path) {
+        let encoder = flate2::write::GzEncoder::new(File::create(path).unwrap(), flate2::Compression::default());
+        let mut builder = tar::Builder::new(encoder);
+        let contents = b"hello from tar\n";
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o755);
+        header.set_mtime(1_000_000_000);
+        header.set_cksum();
+        builder.append_data(&mut header, "./project/run.sh", &contents[..]).unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+    }
+
+    fn names(dir: &Path) -> Vec<String> {
+        get_files_and_dirs(dir).into_iter().map(|file_info| file_info.display_name().into_owned()).collect()
+    }
+
+    #[test]
+    fn test_archive_kind() {
+        assert_eq!(ArchiveKind::from_path(Path::new("a.zip")), Some(ArchiveKind::Zip));
+        assert_eq!(ArchiveKind::from_path(Path::new("a.TAR.GZ")), Some(ArchiveKind::TarGz));
+        assert_eq!(ArchiveKind::from_path(Path::new("a.tar.zst")), Some(ArchiveKind::TarZst));
+        assert_eq!(ArchiveKind::from_path(Path::new("a.tar.xz")), Some(ArchiveKind::TarXz));
+        assert_eq!(ArchiveKind::from_path(Path::new("a.gz")), None);
+        assert_eq!(strip_suffix(Path::new("/tmp/src.tar.gz")).unwrap(), "src");
+        assert_eq!(strip_suffix(Path::new("v1.2.zip")).unwrap(), "v1.2");
+        assert_eq!(strip_suffix(Path::new("a.TGZ")).unwrap(), "a");
+    }
+
+    #[test]
+    fn test_browse_zip() {
+        let dir = tempdir().unwrap();
+        let archive = dir.path().join("test.zip");
+        create_zip(&archive);
+
+        assert!(is_archive(&archive));
+        assert_eq!(names(&archive), vec!["docs", "src", "top.bin"]);
+        assert_eq!(names(&archive.join("src")), vec!["lib"]);
+        assert!(get_files_and_dirs(&archive.join("src")).iter().all(|file_info| file_info.is_dir));
+        assert_eq!(names(&archive.join("src").join("lib")), vec!["mod.rs"]);
+        assert!(list_dir(&archive.join("docs").join("readme.txt")).is_none());
+
+        let contents = read_file(&archive.join("docs").join("readme.txt"), 1024).unwrap().unwrap();
+        assert_eq!(contents.as_slice(), b"first line\nsecond line\n");
+        assert!(read_file(&archive.join("docs"), 1024).is_none());
+    }
+
+    #[test]
+    fn test_browse_tar_gz() {
+        let dir = tempdir().unwrap();
+        let archive = dir.path().join("test.tar.gz");
+        create_tar_gz(&archive);
+
+        assert_eq!(names(&archive), vec!["project"]);
+        let files = get_files_and_dirs(&archive.join("project"));
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].name, "run.sh");
+        assert_eq!(files[0].size, 15);
+        assert!(files[0].modified.is_some());
+
+        let contents = read_file(&archive.join("project").join("run.sh"), 5).unwrap().unwrap();
+        assert_eq!(contents.as_slice(), b"hello");
+    }
+
+    #[test]
+    fn test_read_file_notices_a_changed_archive() {
+        let dir = tempdir().unwrap();
+        let archive = dir.path().join("test.zip");
+        let write_zip = |contents: &[u8]| {
+            let mut writer = zip::ZipWriter::new(File::create(&archive).unwrap());
+            writer.start_file("notes.txt", zip::write::FileOptions::default()).unwrap();
+            writer.write_all(contents).unwrap();
+            writer.finish().unwrap();
+        };
+
+        write_zip(b"old");
+        assert_eq!(read_file(&archive.join("notes.txt"), 1024).unwrap().unwrap().as_slice(), b"old");
+        write_zip(b"newer");
+        assert_eq!(read_file(&archive.join("notes.txt"), 1024).unwrap().unwrap().as_slice(), b"newer");
+    }
+
+    #[test]
+    fn test_fetch_children_of_archive() {
+        let dir = tempdir().unwrap();
+        let archive = dir.path().join("test.zip");
+        create_zip(&archive);
+
+        let (children, _) = fetch_children(&archive, 0, 10, &FileFilter::default());
+        assert_eq!(children.len(), 3);
+
+        let (preview, max_scroll) = fetch_children(&archive.join("docs").join("readme.txt"), 1, 1, &FileFilter::default());
+        assert_eq!(preview[0].name, "second line");
+        assert_eq!(max_scroll, 1);
+
+        let (preview, _) = fetch_children(&archive.join("top.bin"), 0, 10, &FileFilter::default());
+        assert!(preview[0].display_name().starts_with("00000000  00 01 02 03"));
+    }
+
+    #[test]
+    fn test_create_and_extract_archives() {
+        let dir = tempdir().unwrap();
+        let project = dir.path().join("project");
+        fs::create_dir_all(project.join("src")).unwrap();
+        fs::write(project.join("src").join("main.rs"), "fn main() {}\n").unwrap();
+        fs::write(dir.path().join("notes.txt"), "notes").unwrap();
+        let sources = vec![project.clone(), dir.path().join("notes.txt")];
+
+        for name in ["out.zip", "out.tar.gz", "out.tar.zst"] {
+            let archive = dir.path().join(name);
+            let mut files_reported = 0;
+            create_archive(&sources, &archive, &mut |_, files| {
+                files_reported += files;
+                true
+            }).unwrap();
+            assert_eq!(files_reported, 2);
+            assert_eq!(names(&archive), vec!["notes.txt", "project"], "{}", name);
+
+            let dest = dir.path().join(format!("unpacked-{}", name));
+            extract_archive(&archive, &dest, &mut |_, _| true).unwrap();
+            assert_eq!(fs::read_to_string(dest.join("project").join("src").join("main.rs")).unwrap(), "fn main() {}\n");
+            assert_eq!(fs::read_to_string(dest.join("notes.txt")).unwrap(), "notes");
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_utf8_names() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = tempdir().unwrap();
+        let archive = dir.path().join(OsStr::from_bytes(b"caf\xe9.tar"));
+        let mut builder = tar::Builder::new(File::create(&archive).unwrap());
+        let contents = b"bonjour\n";
+        let mut header = tar::Header::new_gnu();
+        header.set_path(OsStr::from_bytes(b"men\xfa.txt")).unwrap();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append(&header, &contents[..]).unwrap();
+        builder.finish().unwrap();
+
+        assert_eq!(ArchiveKind::from_path(&archive), Some(ArchiveKind::Tar));
+        assert_eq!(strip_suffix(&archive).unwrap(), OsStr::from_bytes(b"caf\xe9"));
+
+        let member = archive.join(OsStr::from_bytes(b"men\xfa.txt"));
+        assert_eq!(read_file(&member, 1024).unwrap().unwrap().as_slice(), contents);
+
+        let dest = dir.path().join(strip_suffix(&archive).unwrap());
+        extract_archive(&archive, &dest, &mut |_, _| true).unwrap();
+        assert_eq!(fs::read(dest.join(OsStr::from_bytes(b"men\xfa.txt"))).unwrap(), contents);
+    }
+
+    #[test]
+    fn test_extract_refuses_paths_outside_the_destination() {
+        let dir = tempdir().unwrap();
+        let archive = dir.path().join("evil.zip");
+        let mut writer = zip::ZipWriter::new(File::create(&archive).unwrap());
+        let options = zip::write::FileOptions::default();
+        writer.start_file("../escaped.txt", options).unwrap();
+        writer.write_all(b"nope").unwrap();
+        writer.start_file("kept.txt", options).unwrap();
+        writer.write_all(b"yes").unwrap();
+        writer.finish().unwrap();
+
+        let dest: PathBuf = dir.path().join("evil");
+        extract_archive(&archive, &dest, &mut |_, _| true).unwrap();
+        assert!(!dir.path().join("escaped.txt").exists());
+        assert!(dest.join("kept.txt").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_extract_refuses_writing_through_symlinks() {
+        let dir = tempdir().unwrap();
+        let outside = dir.path().join("outside");
+        fs::create_dir(&outside).unwrap();
+        fs::write(outside.join("victim.txt"), b"untouched").unwrap();
+        let options = zip::write::FileOptions::default();
+
+        // A symlinked directory followed by a member inside of it
+        let archive = dir.path().join("dir_link.zip");
+        let mut writer = zip::ZipWriter::new(File::create(&archive).unwrap());
+        writer.add_symlink("link", outside.to_str().unwrap(), options).unwrap();
+        writer.start_file("link/escaped.txt", options).unwrap();
+        writer.write_all(b"nope").unwrap();
+        writer.finish().unwrap();
+
+        assert!(extract_archive(&archive, &dir.path().join("dir_link"), &mut |_, _| true).is_err());
+        assert!(!outside.join("escaped.txt").exists());
+
+        // A symlinked file followed by a member of the same name
+        let archive = dir.path().join("file_link.zip");
+        let mut writer = zip::ZipWriter::new(File::create(&archive).unwrap());
+        writer.add_symlink("victim.txt", outside.join("victim.txt").to_str().unwrap(), options).unwrap();
+        writer.start_file("victim.txt", options).unwrap();
+        writer.write_all(b"nope").unwrap();
+        writer.finish().unwrap();
+
+        assert!(extract_archive(&archive, &dir.path().join("file_link"), &mut |_, _| true).is_err());
+        assert_eq!(fs::read(outside.join("victim.txt")).unwrap(), b"untouched");
+    }
+}
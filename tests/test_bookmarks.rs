@@ -0,0 +1,40 @@
+#[cfg(test)]
+mod tests {
+    use file_manager::bookmarks::*;
+    use tempfile::tempdir;
+    use std::fs;
+    use std::path::Path;
+
+    #[test]
+    fn test_bookmarks_are_saved_and_reloaded() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("changer").join("bookmarks");
+
+        let mut bookmarks = Bookmarks::open(&file).unwrap();
+        assert!(bookmarks.is_empty());
+        bookmarks.set('p', Path::new("/home/user/my projects")).unwrap();
+        bookmarks.set('a', Path::new("/etc")).unwrap();
+        bookmarks.set('p', Path::new("/srv/projects")).unwrap();
+
+        let reloaded = Bookmarks::open(&file).unwrap();
+        let entries: Vec<_> = reloaded.iter().collect();
+        assert_eq!(entries, vec![('a', Path::new("/etc")), ('p', Path::new("/srv/projects"))]);
+
+        let mut bookmarks = reloaded;
+        bookmarks.remove('a').unwrap();
+        assert_eq!(Bookmarks::open(&file).unwrap().get('a'), None);
+        assert_eq!(bookmarks.len(), 1);
+    }
+
+    #[test]
+    fn test_bookmarks_skip_unreadable_lines() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("bookmarks");
+        fs::write(&file, "a /tmp/with%20space\n1 /digits\nnonsense\nb /usr\n").unwrap();
+
+        let bookmarks = Bookmarks::open(&file).unwrap();
+        assert_eq!(bookmarks.get('a'), Some(Path::new("/tmp/with space")));
+        assert_eq!(bookmarks.get('b'), Some(Path::new("/usr")));
+        assert_eq!(bookmarks.len(), 2);
+    }
+}
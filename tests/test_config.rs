@@ -0,0 +1,134 @@
+#[cfg(test)]
+mod tests {
+    use file_manager::config::*;
+    use file_manager::sort::SortMode;
+    use crossterm::event::{KeyCode, KeyModifiers};
+    use tui::style::Color;
+
+    fn key(c: char) -> KeyPress {
+        KeyPress::new(KeyCode::Char(c), KeyModifiers::NONE)
+    }
+
+    fn lookup(keymap: &Keymap, sequence: &str) -> Option<Action> {
+        match keymap.lookup(&parse_key_sequence(sequence).unwrap()) {
+            KeyLookup::Action(action) => Some(action),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_parse_key_sequence() {
+        assert_eq!(parse_key_sequence("gg").unwrap(), vec![key('g'), key('g')]);
+        assert_eq!(parse_key_sequence("<C-d>").unwrap(), vec![KeyPress::new(KeyCode::Char('d'), KeyModifiers::CONTROL)]);
+        assert_eq!(parse_key_sequence("<S-g>").unwrap(), vec![key('G')]);
+        assert_eq!(
+            parse_key_sequence("<A-G>").unwrap(),
+            vec![KeyPress::new(KeyCode::Char('g'), KeyModifiers::ALT | KeyModifiers::SHIFT)]
+        );
+        assert_eq!(parse_key_sequence("<Space><lt>").unwrap(), vec![key(' '), key('<')]);
+        assert_eq!(parse_key_sequence("<F5>").unwrap(), vec![KeyPress::new(KeyCode::F(5), KeyModifiers::NONE)]);
+
+        assert!(parse_key_sequence("").is_err());
+        assert!(parse_key_sequence("<C-d").is_err());
+        assert!(parse_key_sequence("<Foo>").is_err());
+        assert!(parse_key_sequence("<X-j>").is_err());
+    }
+
+    #[test]
+    fn test_default_keymap_sequences() {
+        let keymap = Keymap::default();
+        assert!(matches!(keymap.lookup(&[key('g')]), KeyLookup::Pending));
+        assert_eq!(lookup(&keymap, "gg"), Some(Action::GoToTop));
+        assert_eq!(lookup(&keymap, "G"), Some(Action::GoToBottom));
+        assert_eq!(lookup(&keymap, "<C-r>"), Some(Action::Redo));
+        assert!(matches!(keymap.lookup(&[key('Z')]), KeyLookup::Unbound));
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let (config, errors) = Config::parse(r##"
+            [keys]
+            move_down = ["<Down>", "j"]
+            go_to_top = "<Home>"
+            quit = "gq"
+
+            [colors]
+            directory = "light_cyan"
+            marked = "#ff8000"
+            file = 250
+            broken_symlink = "magenta"
+
+            [layout]
+            pane_ratios = [1, 2, 2]
+
+            [options]
+            sort = "size"
+            dirs_first = false
+            show_hidden = true
+            ignore = ["*.pyc"]
+            follow_symlinks = true
+        "##);
+
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(lookup(&config.keymap, "<Down>"), Some(Action::MoveDown));
+        assert_eq!(lookup(&config.keymap, "<Home>"), Some(Action::GoToTop));
+        assert_eq!(lookup(&config.keymap, "gq"), Some(Action::Quit));
+        // The defaults of a rebound action are dropped, the rest keep working
+        assert_eq!(lookup(&config.keymap, "gg"), None);
+        assert_eq!(lookup(&config.keymap, "q"), None);
+        assert_eq!(lookup(&config.keymap, "k"), Some(Action::MoveUp));
+
+        assert_eq!(config.theme.directory, Color::LightCyan);
+        assert_eq!(config.theme.marked, Color::Rgb(0xff, 0x80, 0x00));
+        assert_eq!(config.theme.file, Color::Indexed(250));
+        assert_eq!(config.theme.broken_symlink, Color::Magenta);
+        assert_eq!(config.theme.symlink, Color::Cyan);
+        assert_eq!(config.pane_ratios, [1, 2, 2]);
+        assert_eq!(config.sort_options.mode, SortMode::Size);
+        assert!(!config.sort_options.dirs_first);
+        assert!(config.filter.show_hidden);
+        assert!(!config.filter.respect_gitignore);
+        assert!(config.follow_symlinks);
+    }
+
+    #[test]
+    fn test_invalid_entries_are_reported() {
+        let (config, errors) = Config::parse(r#"
+            [keys]
+            fly = "f"
+            copy = "<Bogus>"
+            cut = "zz"
+            paste = "z"
+
+            [colors]
+            directory = "blurple"
+            file = "green"
+
+            [layout]
+            pane_ratios = [1, 2]
+
+            [options]
+            sort = "colour"
+        "#);
+
+        assert_eq!(errors.len(), 6, "{:?}", errors);
+        assert!(errors.iter().any(|e| e.starts_with("keys.fly: unknown action")));
+        assert!(errors.iter().any(|e| e.starts_with("keys.copy: unknown key")));
+        assert!(errors.iter().any(|e| e.starts_with("keys.paste: \"z\" clashes")));
+        assert!(errors.iter().any(|e| e.starts_with("colors.directory: unknown colour")));
+        assert!(errors.iter().any(|e| e.starts_with("layout.pane_ratios:")));
+        assert!(errors.iter().any(|e| e.starts_with("options.sort: unknown sort mode")));
+
+        // Valid entries still apply and invalid ones keep their defaults
+        assert_eq!(config.theme.file, Color::Green);
+        assert_eq!(config.theme.directory, Theme::default().directory);
+        assert_eq!(lookup(&config.keymap, "y"), Some(Action::Copy));
+        assert_eq!(config.pane_ratios, [20, 30, 50]);
+    }
+
+    #[test]
+    fn test_syntax_error() {
+        let (_, errors) = Config::parse("[keys\nquit = 'q'");
+        assert_eq!(errors.len(), 1);
+    }
+}
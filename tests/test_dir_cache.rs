@@ -0,0 +1,47 @@
+#[cfg(test)]
+mod tests {
+    use file_manager::dir_cache::*;
+    use file_manager::fs_utils::{get_files_and_dirs, FileInfo};
+    use tempfile::tempdir;
+    use std::cell::Cell;
+    use std::fs;
+    use std::path::Path;
+
+    // A single test, since the watches are shared and a lookup from a parallel test would take its events
+    #[test]
+    fn test_cached_listing_follows_changes() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("a.txt"), "a").unwrap();
+        assert_eq!(get_files_and_dirs(dir.path()).len(), 1);
+
+        fs::write(dir.path().join("b.txt"), "b").unwrap();
+        assert_eq!(get_files_and_dirs(dir.path()).len(), 2);
+
+        fs::remove_file(dir.path().join("a.txt")).unwrap();
+        let files = get_files_and_dirs(dir.path());
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].name, "b.txt");
+
+        let other_dir = tempdir().unwrap();
+        let reads = Cell::new(0);
+        let read = |_: &Path| -> Vec<FileInfo> {
+            reads.set(reads.get() + 1);
+            Vec::new()
+        };
+
+        get_or_read(other_dir.path(), read);
+        get_or_read(other_dir.path(), read);
+        if cfg!(target_os = "linux") {
+            assert_eq!(reads.get(), 1);
+
+            // A change is reported once, and the next lookup reads the directory again
+            fs::write(other_dir.path().join("new.txt"), "new").unwrap();
+            assert!(poll_changes());
+            assert!(!poll_changes());
+            get_or_read(other_dir.path(), read);
+            assert_eq!(reads.get(), 2);
+        } else {
+            assert_eq!(reads.get(), 2);
+        }
+    }
+}
@@ -0,0 +1,96 @@
+#[cfg(test)]
+mod tests {
+    use file_manager::filter::*;
+    use file_manager::fs_utils::{get_files_and_dirs, FileInfo};
+    use tempfile::tempdir;
+    use std::fs::{self, File};
+    use std::path::Path;
+
+    fn listed_names(filter: &FileFilter, dir: &Path) -> Vec<String> {
+        let mut files: Vec<FileInfo> = get_files_and_dirs(dir);
+        filter.apply(dir, &mut files);
+        let mut names: Vec<String> = files.into_iter().map(|file_info| file_info.display_name().into_owned()).collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_hidden_files() {
+        let dir = tempdir().unwrap();
+        File::create(dir.path().join(".bashrc")).unwrap();
+        File::create(dir.path().join("notes.txt")).unwrap();
+
+        let mut filter = FileFilter::default();
+        assert_eq!(listed_names(&filter, dir.path()), vec!["notes.txt"]);
+
+        filter.show_hidden = true;
+        assert_eq!(listed_names(&filter, dir.path()), vec![".bashrc", "notes.txt"]);
+    }
+
+    #[test]
+    fn test_ignore_globs() {
+        let dir = tempdir().unwrap();
+        File::create(dir.path().join("main.py")).unwrap();
+        File::create(dir.path().join("main.pyc")).unwrap();
+        fs::create_dir(dir.path().join("__pycache__")).unwrap();
+
+        let mut filter = FileFilter::default();
+        filter.set_ignore_globs(&["*.pyc", "__pycache__"]).unwrap();
+        assert_eq!(listed_names(&filter, dir.path()), vec!["main.py"]);
+
+        assert!(filter.set_ignore_globs(&["[unclosed"]).is_err());
+    }
+
+    #[test]
+    fn test_gitignore() {
+        let repo = tempdir().unwrap();
+        fs::create_dir(repo.path().join(".git")).unwrap();
+        fs::write(repo.path().join(".gitignore"), "*.log\nbuild/\n").unwrap();
+        fs::create_dir(repo.path().join("build")).unwrap();
+        File::create(repo.path().join("debug.log")).unwrap();
+        File::create(repo.path().join("main.rs")).unwrap();
+
+        let sub_dir = repo.path().join("src");
+        fs::create_dir(&sub_dir).unwrap();
+        fs::write(sub_dir.join(".gitignore"), "!keep.log\n").unwrap();
+        File::create(sub_dir.join("keep.log")).unwrap();
+        File::create(sub_dir.join("other.log")).unwrap();
+
+        let mut filter = FileFilter::default();
+        assert_eq!(listed_names(&filter, repo.path()), vec!["build", "debug.log", "main.rs", "src"]);
+
+        filter.respect_gitignore = true;
+        assert_eq!(listed_names(&filter, repo.path()), vec!["main.rs", "src"]);
+        // The nested .gitignore re-includes one file that the root one ignores
+        assert_eq!(listed_names(&filter, &sub_dir), vec!["keep.log"]);
+    }
+
+    #[test]
+    fn test_gitignore_inside_ignored_dir() {
+        let repo = tempdir().unwrap();
+        fs::create_dir(repo.path().join(".git")).unwrap();
+        fs::write(repo.path().join(".gitignore"), "build/
+").unwrap();
+        let build_dir = repo.path().join("build");
+        fs::create_dir_all(build_dir.join("debug")).unwrap();
+        File::create(build_dir.join("output.bin")).unwrap();
+        File::create(build_dir.join("debug").join("trace.txt")).unwrap();
+
+        let mut filter = FileFilter::default();
+        filter.respect_gitignore = true;
+        // Everything inside an ignored directory is ignored along with it
+        assert!(listed_names(&filter, &build_dir).is_empty());
+        assert!(listed_names(&filter, &build_dir.join("debug")).is_empty());
+    }
+
+    #[test]
+    fn test_gitignore_outside_repository() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join(".gitignore"), "*.log\n").unwrap();
+        File::create(dir.path().join("debug.log")).unwrap();
+
+        let mut filter = FileFilter::default();
+        filter.respect_gitignore = true;
+        assert_eq!(listed_names(&filter, dir.path()), vec!["debug.log"]);
+    }
+}
@@ -1,6 +1,7 @@
 #[cfg(test)]
 mod tests {
     use file_manager::fs_utils::*;
+    use file_manager::filter::FileFilter;
     use tempfile::tempdir;
     use std::{fs::{File, self}, path::PathBuf};
     use tui::widgets::ListState;
@@ -17,6 +18,7 @@ mod tests {
                 perms: None,
                 is_dir: false,
                 is_exec: false,
+                ..Default::default()
             },
         ];
 
@@ -33,7 +35,7 @@ mod tests {
     #[test]
     fn test_fetch_children_for_empty_dir() {
         let dir = tempdir().unwrap();
-        let children = fetch_children(&dir.path().to_path_buf(), 0, 5);
+        let children = fetch_children(&dir.path().to_path_buf(), 0, 5, &FileFilter::default());
 
         assert_eq!(children.0[0].name, "empty");
         assert_eq!(children.1, 0);
@@ -44,7 +46,7 @@ mod tests {
         let dir = tempdir().unwrap();
         File::create(dir.path().join("test.txt")).unwrap();
 
-        let children = fetch_children(&dir.path().to_path_buf(), 0, 5);
+        let children = fetch_children(&dir.path().to_path_buf(), 0, 5, &FileFilter::default());
         assert_eq!(children.0[0].name, "test.txt");
     }
 
@@ -128,8 +130,8 @@ mod tests {
     fn test_create_file_info() {
         let name = "test_file".to_string();
         let info = create_file_info(name.clone());
-        assert_eq!(info.name, name);
-        assert_eq!(info.is_dir, false);
+        assert_eq!(info.name, name.as_str());
+        assert!(!info.is_dir);
     }
 
     #[test]
@@ -156,4 +158,361 @@ mod tests {
         delete(&subdir).unwrap();
         assert!(!subdir.exists());
     }
+
+    #[test]
+    fn test_get_tree_size() {
+        let dir = tempdir().unwrap();
+        let subdir = dir.path().join("subdir");
+        fs::create_dir(&subdir).unwrap();
+        fs::write(dir.path().join("test.txt"), b"12345").unwrap();
+        fs::write(subdir.join("test.txt"), b"123").unwrap();
+
+        assert_eq!(get_tree_size(dir.path()), (8, 2));
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(3 * 1024 * 1024), "3.0 MiB");
+    }
+
+    #[test]
+    fn test_copy_large_file_in_chunks() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("large.bin");
+        let dest = dir.path().join("large_copy.bin");
+        let contents: Vec<u8> = (0..3 * 1024 * 1024 + 17).map(|i| (i % 251) as u8).collect();
+        fs::write(&src, &contents).unwrap();
+
+        let mut reported_bytes = 0;
+        let mut reported_files = 0;
+        copy_with_progress(&src, &dest, ConflictPolicy::Rename, &mut |bytes, files| {
+            reported_bytes += bytes;
+            reported_files += files;
+            true
+        }).unwrap();
+
+        assert_eq!(fs::read(&dest).unwrap(), contents);
+        assert_eq!(reported_bytes, contents.len() as u64);
+        assert_eq!(reported_files, 1);
+    }
+
+    #[test]
+    fn test_cancel_copy() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("test.txt");
+        fs::write(&src, b"contents").unwrap();
+
+        let result = copy_with_progress(&src, &dir.path().join("copy.txt"), ConflictPolicy::Rename, &mut |_, _| false);
+        assert!(is_cancelled(&result.unwrap_err()));
+        assert!(!dir.path().join("copy.txt").exists());
+    }
+
+    #[test]
+    fn test_cancelled_overwrite_keeps_destination() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("test.txt");
+        fs::write(&src, b"new contents").unwrap();
+        let dest_file = dir.path().join("dest.txt");
+        fs::write(&dest_file, b"old contents").unwrap();
+        let dest_dir = dir.path().join("dest");
+        fs::create_dir(&dest_dir).unwrap();
+        fs::write(dest_dir.join("inner.txt"), b"inner").unwrap();
+
+        let result = copy_with_progress(&src, &dest_file, ConflictPolicy::Overwrite, &mut |_, _| false);
+        assert!(is_cancelled(&result.unwrap_err()));
+        assert_eq!(fs::read(&dest_file).unwrap(), b"old contents");
+
+        // A file pasted over a directory of the same name must not take the tree with it
+        let result = copy_with_progress(&src, &dest_dir, ConflictPolicy::Overwrite, &mut |_, _| false);
+        assert!(is_cancelled(&result.unwrap_err()));
+        assert_eq!(fs::read(dest_dir.join("inner.txt")).unwrap(), b"inner");
+
+        // Nothing but the source and the two destinations is left over
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 3);
+
+        copy_with_progress(&src, &dest_dir, ConflictPolicy::Overwrite, &mut |_, _| true).unwrap();
+        assert_eq!(fs::read(&dest_dir).unwrap(), b"new contents");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 3);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_copy_preserves_metadata() {
+        use std::os::unix::fs::PermissionsExt;
+        use std::time::{Duration, SystemTime};
+
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("script.sh");
+        let dest = dir.path().join("script_copy.sh");
+        fs::write(&src, b"#!/bin/sh").unwrap();
+        fs::set_permissions(&src, fs::Permissions::from_mode(0o750)).unwrap();
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        File::options().write(true).open(&src).unwrap().set_modified(modified).unwrap();
+
+        paste_file(&src, &dest).unwrap();
+
+        let metadata = fs::metadata(&dest).unwrap();
+        assert_eq!(metadata.permissions().mode() & 0o777, 0o750);
+        assert_eq!(metadata.modified().unwrap(), modified);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_copy_dir_to_keeps_symlinks() {
+        let src_dir = tempdir().unwrap();
+        let dest_dir = tempdir().unwrap();
+        let outside = tempdir().unwrap();
+        File::create(outside.path().join("secret.txt")).unwrap();
+        std::os::unix::fs::symlink(outside.path(), src_dir.path().join("link")).unwrap();
+
+        copy_dir_to(src_dir.path(), dest_dir.path()).unwrap();
+
+        let copied_link = dest_dir.path().join("link");
+        assert!(fs::symlink_metadata(&copied_link).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read_link(&copied_link).unwrap(), outside.path());
+    }
+
+    #[test]
+    fn test_copy_conflict_skip_and_rename() {
+        let dir = tempdir().unwrap();
+        let src_dir = dir.path().join("src");
+        fs::create_dir(&src_dir).unwrap();
+        let src = src_dir.join("test.txt");
+        let dest = dir.path().join("test.txt");
+        fs::write(&src, b"new").unwrap();
+        fs::write(&dest, b"old").unwrap();
+
+        let skipped = copy_with_progress(&src, &dest, ConflictPolicy::Skip, &mut |_, _| true).unwrap();
+        assert_eq!(skipped, None);
+        assert_eq!(fs::read(&dest).unwrap(), b"old");
+
+        let renamed = copy_with_progress(&src, &dest, ConflictPolicy::Rename, &mut |_, _| true).unwrap().unwrap();
+        assert_ne!(renamed, dest);
+        assert_eq!(fs::read(&renamed).unwrap(), b"new");
+        assert_eq!(fs::read(&dest).unwrap(), b"old");
+    }
+
+    #[test]
+    fn test_copy_conflict_overwrite_if_newer() {
+        use std::time::{Duration, SystemTime};
+
+        let dir = tempdir().unwrap();
+        let src_dir = dir.path().join("src");
+        fs::create_dir(&src_dir).unwrap();
+        let src = src_dir.join("test.txt");
+        let dest = dir.path().join("test.txt");
+        fs::write(&src, b"new").unwrap();
+        fs::write(&dest, b"old").unwrap();
+
+        let old_time = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        File::options().write(true).open(&src).unwrap().set_modified(old_time).unwrap();
+        assert_eq!(copy_with_progress(&src, &dest, ConflictPolicy::OverwriteIfNewer, &mut |_, _| true).unwrap(), None);
+        assert_eq!(fs::read(&dest).unwrap(), b"old");
+
+        File::options().write(true).open(&dest).unwrap().set_modified(old_time - Duration::from_secs(60)).unwrap();
+        copy_with_progress(&src, &dest, ConflictPolicy::OverwriteIfNewer, &mut |_, _| true).unwrap();
+        assert_eq!(fs::read(&dest).unwrap(), b"new");
+    }
+
+    #[test]
+    fn test_move_merges_into_existing_dir() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src").join("data");
+        let dest = dir.path().join("data");
+        fs::create_dir_all(&src).unwrap();
+        fs::create_dir(&dest).unwrap();
+        fs::write(src.join("a.txt"), b"new a").unwrap();
+        fs::write(src.join("b.txt"), b"b").unwrap();
+        fs::write(dest.join("a.txt"), b"old a").unwrap();
+        fs::write(dest.join("c.txt"), b"c").unwrap();
+
+        move_with_progress(&src, &dest, ConflictPolicy::Overwrite, &mut |_, _| true).unwrap();
+
+        assert!(!src.exists());
+        assert_eq!(fs::read(dest.join("a.txt")).unwrap(), b"new a");
+        assert!(dest.join("b.txt").exists());
+        assert!(dest.join("c.txt").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_file_info_metadata() {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("test.txt");
+        fs::write(&file_path, b"hello").unwrap();
+        fs::set_permissions(&file_path, fs::Permissions::from_mode(0o640)).unwrap();
+        std::os::unix::fs::symlink("test.txt", dir.path().join("link")).unwrap();
+
+        let files = get_files_and_dirs(dir.path());
+        let file = files.iter().find(|file_info| file_info.name == "test.txt").unwrap();
+        let link = files.iter().find(|file_info| file_info.name == "link").unwrap();
+
+        assert_eq!(file.uid, Some(fs::metadata(&file_path).unwrap().uid()));
+        assert_eq!(get_octal_permissions(file.perms.as_ref().unwrap()), "0640");
+        assert!(get_owner(file).is_some());
+        assert_eq!(file.symlink_target, None);
+        assert_eq!(link.symlink_target, Some(PathBuf::from("test.txt")));
+
+        let status = file_manager::ui::status_line(file, 2, 3);
+        assert!(status.starts_with(" 2/3  rw-r----- (0640)  "));
+        assert!(status.contains("5 B"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_utf8_file_names() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = tempdir().unwrap();
+        let name = OsStr::from_bytes(b"bad\xffname.txt");
+        let sub_dir = dir.path().join(OsStr::from_bytes(b"dir\xfe"));
+        fs::write(dir.path().join(name), b"hello").unwrap();
+        fs::create_dir(&sub_dir).unwrap();
+        File::create(sub_dir.join(name)).unwrap();
+
+        let mut files = get_files_and_dirs(dir.path());
+        file_manager::sort::sort_files(&mut files, &Default::default());
+        assert_eq!(files.len(), 2);
+
+        // The raw name leads back to the file, the display name is only for the screen
+        let file = files.iter().find(|file_info| !file_info.is_dir).unwrap();
+        assert_eq!(file.name, name);
+        assert_eq!(file.display_name(), "bad\u{fffd}name.txt");
+        assert_eq!(fs::read(dir.path().join(&file.name)).unwrap(), b"hello");
+
+        let (children, _) = fetch_children(&sub_dir, 0, 10, &FileFilter::default());
+        assert_eq!(children[0].name, name);
+
+        let dest_dir = tempdir().unwrap();
+        let dest = dest_dir.path().join(&file.name);
+        copy_with_progress(&dir.path().join(&file.name), &dest, ConflictPolicy::Rename, &mut |_, _| true).unwrap();
+        assert_eq!(fs::read(&dest).unwrap(), b"hello");
+
+        // A numbered copy keeps the raw bytes of the name
+        let renamed = copy_with_progress(&dir.path().join(&file.name), &dest, ConflictPolicy::Rename, &mut |_, _| true).unwrap().unwrap();
+        assert_eq!(renamed.file_name().unwrap(), OsStr::from_bytes(b"bad\xffname_1.txt"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinks_are_listed_and_deleted_as_links() {
+        use std::os::unix::fs::symlink;
+
+        let dir = tempdir().unwrap();
+        let target_dir = dir.path().join("target");
+        fs::create_dir(&target_dir).unwrap();
+        fs::write(target_dir.join("kept.txt"), "kept").unwrap();
+        symlink(&target_dir, dir.path().join("dir_link")).unwrap();
+        symlink(dir.path().join("missing"), dir.path().join("broken_link")).unwrap();
+
+        let files = get_files_and_dirs(dir.path());
+        let dir_link = files.iter().find(|file_info| file_info.name == "dir_link").unwrap();
+        let broken_link = files.iter().find(|file_info| file_info.name == "broken_link").unwrap();
+        let target = files.iter().find(|file_info| file_info.name == "target").unwrap();
+
+        // A link to a directory can be entered like one
+        assert!(dir_link.is_symlink && dir_link.is_dir && !dir_link.is_broken_link);
+        assert!(broken_link.is_symlink && broken_link.is_broken_link && !broken_link.is_dir);
+        assert!(!target.is_symlink);
+
+        delete(&dir.path().join("dir_link")).unwrap();
+        delete(&dir.path().join("broken_link")).unwrap();
+        assert!(dir.path().join("dir_link").symlink_metadata().is_err());
+        assert!(dir.path().join("broken_link").symlink_metadata().is_err());
+        assert_eq!(fs::read_to_string(target_dir.join("kept.txt")).unwrap(), "kept");
+
+        // A broken link still occupies its name
+        symlink(dir.path().join("missing"), dir.path().join("taken")).unwrap();
+        assert_eq!(make_unique_path(dir.path().join("taken")), dir.path().join("taken_1"));
+    }
+
+    #[test]
+    fn test_relative_path() {
+        assert_eq!(relative_path(&PathBuf::from("/x/bin"), &PathBuf::from("/x/lib/a.so")), PathBuf::from("../lib/a.so"));
+        assert_eq!(relative_path(&PathBuf::from("/x"), &PathBuf::from("/x/a")), PathBuf::from("a"));
+        assert_eq!(relative_path(&PathBuf::from("/x/y/z"), &PathBuf::from("/a")), PathBuf::from("../../../a"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_create_links() {
+        use std::os::unix::fs::MetadataExt;
+
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("dotfiles").join("vimrc");
+        fs::create_dir(dir.path().join("dotfiles")).unwrap();
+        fs::create_dir(dir.path().join("home")).unwrap();
+        fs::write(&src, "set number").unwrap();
+
+        let absolute = dir.path().join("home").join("absolute");
+        create_link(&src, &absolute, LinkKind::AbsoluteSymlink).unwrap();
+        assert!(fs::read_link(&absolute).unwrap().is_absolute());
+        assert_eq!(fs::read_to_string(&absolute).unwrap(), "set number");
+
+        let relative = dir.path().join("home").join("relative");
+        create_link(&src, &relative, LinkKind::RelativeSymlink).unwrap();
+        assert_eq!(fs::read_link(&relative).unwrap(), PathBuf::from("../dotfiles/vimrc"));
+        assert_eq!(fs::read_to_string(&relative).unwrap(), "set number");
+
+        let hard = dir.path().join("home").join("hard");
+        create_link(&src, &hard, LinkKind::HardLink).unwrap();
+        assert!(!hard.symlink_metadata().unwrap().file_type().is_symlink());
+        assert_eq!(hard.metadata().unwrap().ino(), src.metadata().unwrap().ino());
+    }
+
+    #[test]
+    fn test_is_within() {
+        let dir = tempdir().unwrap();
+        let a = dir.path().join("a");
+        fs::create_dir_all(a.join("b")).unwrap();
+        File::create(dir.path().join("file")).unwrap();
+
+        assert!(is_within(&a, &a));
+        assert!(is_within(&a.join("b"), &a));
+        assert!(is_within(&a.join("b").join("not_yet"), &a));
+        assert!(!is_within(dir.path(), &a));
+        assert!(!is_within(&dir.path().join("a_1"), &a));
+        assert!(!is_within(&dir.path().join("file"), &dir.path().join("file")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_is_within_sees_through_symlinks() {
+        let dir = tempdir().unwrap();
+        let a = dir.path().join("a");
+        fs::create_dir(&a).unwrap();
+        let link = dir.path().join("link");
+        std::os::unix::fs::symlink(&a, &link).unwrap();
+
+        // Going into the directory through a link still lands inside it
+        assert!(is_within(&link.join("copy"), &a));
+        // A link to a directory is copied as a link, so pasting it into its target is fine
+        assert!(!is_within(&a, &link));
+    }
+
+    #[test]
+    fn test_copy_and_move_into_itself_are_refused() {
+        let dir = tempdir().unwrap();
+        let a = dir.path().join("a");
+        fs::create_dir(&a).unwrap();
+        File::create(a.join("file.txt")).unwrap();
+
+        for policy in [ConflictPolicy::Rename, ConflictPolicy::Overwrite] {
+            let error = copy_with_progress(&a, &a.join("a"), policy, &mut |_, _| true).unwrap_err();
+            assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+            let error = move_with_progress(&a, &a.join("a"), policy, &mut |_, _| true).unwrap_err();
+            assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+        }
+        assert!(!a.join("a").exists());
+        assert!(a.join("file.txt").exists());
+
+        // Copying next to itself picks a fresh name rather than going inside
+        let copy = copy_with_progress(&a, &a, ConflictPolicy::Rename, &mut |_, _| true).unwrap().unwrap();
+        assert!(copy.join("file.txt").exists());
+    }
 }
\ No newline at end of file
@@ -0,0 +1,53 @@
+#[cfg(test)]
+mod tests {
+    use file_manager::highlight::*;
+    use tempfile::tempdir;
+    use std::fs;
+
+    #[test]
+    fn test_detect_syntax() {
+        let dir = tempdir().unwrap();
+        let rust_file = dir.path().join("main.rs");
+        let script = dir.path().join("run");
+        let notes = dir.path().join("notes");
+        fs::write(&rust_file, "fn main() {}\n").unwrap();
+        fs::write(&script, "#!/bin/bash\necho hi\n").unwrap();
+        fs::write(&notes, "just some words\n").unwrap();
+
+        assert_eq!(detect_syntax(&rust_file).unwrap().name, "Rust");
+        assert_eq!(detect_syntax(&script).unwrap().name, "Bourne Again Shell (bash)");
+        assert!(detect_syntax(&notes).is_none());
+    }
+
+    #[test]
+    fn test_detect_syntax_notices_a_changed_file() {
+        let dir = tempdir().unwrap();
+        let script = dir.path().join("run");
+        fs::write(&script, "echo hi\n").unwrap();
+        filetime::set_file_mtime(&script, filetime::FileTime::from_unix_time(1_000_000_000, 0)).unwrap();
+        assert!(detect_syntax(&script).is_none());
+
+        fs::write(&script, "#!/bin/bash\necho hi\n").unwrap();
+        assert_eq!(detect_syntax(&script).unwrap().name, "Bourne Again Shell (bash)");
+    }
+
+    #[test]
+    fn test_highlight_keeps_the_text() {
+        let dir = tempdir().unwrap();
+        let rust_file = dir.path().join("main.rs");
+        fs::write(&rust_file, "").unwrap();
+        let syntax = detect_syntax(&rust_file).unwrap();
+
+        let text = "fn main() {\n    let x = 1; // one\n}";
+        let lines = highlight(text, syntax);
+
+        assert_eq!(lines.len(), 3);
+        for (spans, line) in lines.iter().zip(text.lines()) {
+            let content: String = spans.0.iter().map(|span| span.content.as_ref()).collect();
+            assert_eq!(content, line);
+        }
+        // The keyword and the comment are styled differently
+        let styles: Vec<_> = lines[1].0.iter().map(|span| span.style).collect();
+        assert!(styles.windows(2).any(|pair| pair[0] != pair[1]));
+    }
+}
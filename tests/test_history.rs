@@ -0,0 +1,44 @@
+#[cfg(test)]
+mod tests {
+    use file_manager::history::*;
+    use std::ffi::OsStr;
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn test_back_and_forward() {
+        let mut history = History::default();
+        history.visit(Path::new("/a"));
+        history.visit(Path::new("/a/b"));
+        // Now in /a/b/c
+
+        assert_eq!(history.go_back(Path::new("/a/b/c"), |_| true), Some(PathBuf::from("/a/b")));
+        assert_eq!(history.go_back(Path::new("/a/b"), |_| true), Some(PathBuf::from("/a")));
+        assert_eq!(history.go_back(Path::new("/a"), |_| true), None);
+        assert_eq!(history.go_forward(Path::new("/a"), |_| true), Some(PathBuf::from("/a/b")));
+
+        // Visiting somewhere new from the middle of the history drops the way forward
+        history.visit(Path::new("/a/b"));
+        assert_eq!(history.go_forward(Path::new("/x"), |_| true), None);
+        assert_eq!(history.go_back(Path::new("/x"), |_| true), Some(PathBuf::from("/a/b")));
+    }
+
+    #[test]
+    fn test_unusable_directories_are_skipped() {
+        let mut history = History::default();
+        history.visit(Path::new("/kept"));
+        history.visit(Path::new("/deleted"));
+
+        let exists = |dir: &Path| dir != Path::new("/deleted");
+        assert_eq!(history.go_back(Path::new("/here"), exists), Some(PathBuf::from("/kept")));
+        assert_eq!(history.go_back(Path::new("/kept"), exists), None);
+    }
+
+    #[test]
+    fn test_remembered_selection() {
+        let mut history = History::default();
+        assert_eq!(history.selection(Path::new("/a")), None);
+        history.remember_selection(Path::new("/a"), OsStr::new("first"));
+        history.remember_selection(Path::new("/a"), OsStr::new("second"));
+        assert_eq!(history.selection(Path::new("/a")), Some(OsStr::new("second")));
+    }
+}
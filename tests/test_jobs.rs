@@ -0,0 +1,89 @@
+#[cfg(test)]
+mod tests {
+    use file_manager::jobs::*;
+    use file_manager::fs_utils::ConflictPolicy;
+    use file_manager::journal::Operation;
+    use tempfile::tempdir;
+    use std::fs::{self, File};
+    use std::io::Write;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    fn wait_for_outcomes(manager: &mut JobManager, count: usize) -> Vec<JobOutcome> {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let mut outcomes = Vec::new();
+        while outcomes.len() < count {
+            outcomes.extend(manager.poll());
+            assert!(Instant::now() < deadline, "job did not finish in time");
+            thread::sleep(Duration::from_millis(10));
+        }
+        outcomes
+    }
+
+    #[test]
+    fn test_copy_job() {
+        let src_dir = tempdir().unwrap();
+        let dest_dir = tempdir().unwrap();
+        let sub_dir = src_dir.path().join("subdir");
+        fs::create_dir(&sub_dir).unwrap();
+        File::create(sub_dir.join("test.txt")).unwrap().write_all(b"hello").unwrap();
+
+        let dest = dest_dir.path().join("subdir");
+        let mut manager = JobManager::new();
+        manager.submit(JobTask::Copy(vec![(sub_dir.clone(), dest.clone(), ConflictPolicy::Rename)]));
+        assert!(manager.is_busy());
+
+        let outcome = wait_for_outcomes(&mut manager, 1).remove(0);
+        assert!(outcome.error.is_none());
+        assert!(!manager.is_busy());
+        assert_eq!(fs::read_to_string(dest.join("test.txt")).unwrap(), "hello");
+        assert!(matches!(outcome.undo_operations.as_slice(), [Operation::RemoveCopy { .. }]));
+    }
+
+    #[test]
+    fn test_move_and_delete_jobs() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("test.txt");
+        let dest = dir.path().join("moved.txt");
+        File::create(&src).unwrap();
+
+        let mut manager = JobManager::new();
+        manager.submit(JobTask::Move(vec![(src.clone(), dest.clone(), ConflictPolicy::Rename)]));
+        manager.submit(JobTask::Delete(vec![dest.clone()]));
+
+        let outcomes = wait_for_outcomes(&mut manager, 2);
+        assert!(outcomes.iter().all(|outcome| outcome.error.is_none()));
+        assert!(matches!(outcomes[0].undo_operations.as_slice(), [Operation::Move { .. }]));
+        assert!(outcomes[1].undo_operations.is_empty());
+
+        assert!(!src.exists());
+        assert!(!dest.exists());
+    }
+
+    #[test]
+    fn test_archive_jobs_pick_unique_names() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("data");
+        fs::create_dir(&src).unwrap();
+        fs::write(src.join("test.txt"), "hello").unwrap();
+        // Both the archive and the directory it unpacks into are already taken
+        fs::write(dir.path().join("data.zip"), "taken").unwrap();
+
+        let mut manager = JobManager::new();
+        manager.submit(JobTask::Compress(vec![src.clone()], dir.path().join("data.zip")));
+        manager.submit(JobTask::Extract(vec![(dir.path().join("data_1.zip"), dir.path().join("data"))]));
+
+        let outcomes = wait_for_outcomes(&mut manager, 2);
+        assert!(outcomes.iter().all(|outcome| outcome.error.is_none()));
+        assert_eq!(fs::read_to_string(dir.path().join("data.zip")).unwrap(), "taken");
+        assert_eq!(fs::read_to_string(dir.path().join("data_1").join("data").join("test.txt")).unwrap(), "hello");
+        assert!(matches!(outcomes[1].undo_operations.as_slice(), [Operation::Trash { path }] if path.ends_with("data_1")));
+    }
+
+    #[test]
+    fn test_job_progress_ratio() {
+        let progress = JobProgress { bytes_done: 25, bytes_total: 100, files_done: 1, files_total: 4 };
+        assert_eq!(progress.ratio(), 0.25);
+        assert_eq!(JobProgress::default().ratio(), 0.0);
+    }
+}
@@ -0,0 +1,151 @@
+#[cfg(test)]
+mod tests {
+    use file_manager::journal::*;
+    use tempfile::tempdir;
+    use std::fs::{self, File};
+
+    #[test]
+    fn test_undo_redo_move() {
+        let dir = tempdir().unwrap();
+        let old_path = dir.path().join("old.txt");
+        let new_path = dir.path().join("new.txt");
+        File::create(&old_path).unwrap();
+        fs::rename(&old_path, &new_path).unwrap();
+
+        let mut journal = Journal::default();
+        journal.record(vec![Operation::Move { src: new_path.clone(), dest: old_path.clone() }]);
+
+        journal.undo().unwrap().unwrap();
+        assert!(old_path.exists());
+        assert!(!new_path.exists());
+
+        journal.redo().unwrap().unwrap();
+        assert!(!old_path.exists());
+        assert!(new_path.exists());
+
+        assert!(journal.redo().is_none());
+    }
+
+    #[test]
+    fn test_undo_copy_removes_the_copy() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("test.txt");
+        let dest = dir.path().join("test_1.txt");
+        File::create(&src).unwrap();
+        fs::copy(&src, &dest).unwrap();
+
+        let mut journal = Journal::default();
+        journal.record(vec![Operation::RemoveCopy { src: src.clone(), dest: dest.clone() }]);
+
+        journal.undo().unwrap().unwrap();
+        assert!(src.exists());
+        assert!(!dest.exists());
+
+        journal.redo().unwrap().unwrap();
+        assert!(dest.exists());
+    }
+
+    #[test]
+    fn test_undo_batch_in_reverse_order() {
+        let dir = tempdir().unwrap();
+        let a = dir.path().join("a");
+        let b = dir.path().join("b");
+        let c = dir.path().join("c");
+        File::create(&c).unwrap();
+
+        // a -> b, then b -> c; undoing has to move c back to b before b can go back to a
+        let mut journal = Journal::default();
+        journal.record(vec![
+            Operation::Move { src: b.clone(), dest: a.clone() },
+            Operation::Move { src: c.clone(), dest: b.clone() },
+        ]);
+
+        journal.undo().unwrap().unwrap();
+        assert!(a.exists());
+        assert!(!b.exists());
+        assert!(!c.exists());
+    }
+
+    #[test]
+    fn test_failed_undo_can_be_retried() {
+        let dir = tempdir().unwrap();
+        let a = dir.path().join("a");
+        let b = dir.path().join("b");
+        let c = dir.path().join("c");
+        let d = dir.path().join("d");
+        File::create(&b).unwrap();
+        File::create(&d).unwrap();
+        // Something else took the original name, so moving d back to c fails
+        File::create(&c).unwrap();
+
+        let mut journal = Journal::default();
+        journal.record(vec![
+            Operation::Move { src: b.clone(), dest: a.clone() },
+            Operation::Move { src: d.clone(), dest: c.clone() },
+        ]);
+
+        assert!(journal.undo().unwrap().is_err());
+        assert!(b.exists());
+        assert!(d.exists());
+
+        // The whole group is still there to undo once the way is clear
+        fs::remove_file(&c).unwrap();
+        journal.undo().unwrap().unwrap();
+        assert!(a.exists());
+        assert!(c.exists());
+        assert!(journal.undo().is_none());
+    }
+
+    #[test]
+    fn test_partly_failed_undo_keeps_the_remainder() {
+        let dir = tempdir().unwrap();
+        let a = dir.path().join("a");
+        let b = dir.path().join("b");
+        let c = dir.path().join("c");
+        let d = dir.path().join("d");
+        File::create(&a).unwrap();
+        File::create(&b).unwrap();
+        File::create(&d).unwrap();
+
+        // Undoing runs the last operation first: d -> c works, b -> a doesn't
+        let mut journal = Journal::default();
+        journal.record(vec![
+            Operation::Move { src: b.clone(), dest: a.clone() },
+            Operation::Move { src: d.clone(), dest: c.clone() },
+        ]);
+
+        assert!(journal.undo().unwrap().is_err());
+        assert!(c.exists());
+
+        fs::remove_file(&a).unwrap();
+        journal.undo().unwrap().unwrap();
+        assert!(a.exists());
+        assert!(!b.exists());
+
+        // Each part is redone separately, the retried part first
+        journal.redo().unwrap().unwrap();
+        assert!(b.exists());
+        journal.redo().unwrap().unwrap();
+        assert!(d.exists());
+        assert!(journal.redo().is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_undo_chmod() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.txt");
+        File::create(&path).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+
+        let undo_operation = Operation::Chmod { path: path.clone(), mode: 0o600 }.perform().unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+
+        let mut journal = Journal::default();
+        journal.record(vec![undo_operation]);
+        journal.undo().unwrap().unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o644);
+    }
+}
@@ -0,0 +1,84 @@
+#[cfg(test)]
+mod tests {
+    use file_manager::preview::*;
+    use tempfile::tempdir;
+    use std::fs;
+
+    #[test]
+    fn test_hex_dump_preview() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("data.bin");
+        let mut contents = b"Hello\0world".to_vec();
+        contents.extend((0..30).map(|i| i as u8));
+        fs::write(&path, &contents).unwrap();
+
+        let (preview, max_scroll) = get_file_preview(&path, 0, 2).unwrap();
+        let lines: Vec<&str> = preview.lines().collect();
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], "00000000  48 65 6c 6c 6f 00 77 6f  72 6c 64 00 01 02 03 04  |Hello.world.....|");
+        assert!(lines[1].starts_with("00000010  05 06 07"));
+        // 41 bytes make three lines, one more than fits
+        assert_eq!(max_scroll, 1);
+    }
+
+    #[test]
+    fn test_hex_dump_scrolls_to_the_last_line() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("data.bin");
+        let contents: Vec<u8> = (0..=255).chain(0..=2).collect();
+        fs::write(&path, &contents).unwrap();
+
+        let (preview, max_scroll) = get_file_preview(&path, 100, 4).unwrap();
+        let lines: Vec<&str> = preview.lines().collect();
+
+        assert_eq!(max_scroll, 13);
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("000000d0  d0 d1"));
+        assert_eq!(lines[3], format!("00000100  00 01 02 {}|...|", " ".repeat(3 * 13 + 2)));
+    }
+
+    #[test]
+    fn test_text_preview_reads_the_visible_window() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("log.txt");
+        let contents: String = (0..1000).map(|i| format!("line {}\r\n", i)).collect();
+        fs::write(&path, contents).unwrap();
+
+        let (preview, max_scroll) = get_file_preview(&path, 500, 3).unwrap();
+        assert_eq!(preview, "line 500\nline 501\nline 502");
+        assert_eq!(max_scroll, 997);
+
+        // Scrolling past the end shows the last lines
+        let (preview, _) = get_file_preview(&path, 5000, 2).unwrap();
+        assert_eq!(preview, "line 998\nline 999");
+
+        // A changed file is indexed again
+        fs::write(&path, "first\nsecond").unwrap();
+        let (preview, max_scroll) = get_file_preview(&path, 0, 5).unwrap();
+        assert_eq!(preview, "first\nsecond");
+        assert_eq!(max_scroll, 0);
+    }
+
+    #[test]
+    fn test_huge_text_preview_is_indexed_incrementally() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("huge.log");
+        let line = format!("{}\n", "x".repeat(99));
+        fs::write(&path, line.repeat(200_000)).unwrap();
+
+        // Only part of the 20 MB is scanned at first, and the rest on later previews
+        let (preview, first_max_scroll) = get_file_preview(&path, 0, 10).unwrap();
+        assert_eq!(preview.lines().count(), 10);
+        assert!(first_max_scroll < 200_000 - 10);
+
+        let mut max_scroll = first_max_scroll;
+        for _ in 0..10 {
+            max_scroll = get_file_preview(&path, 0, 10).unwrap().1;
+        }
+        assert_eq!(max_scroll, 200_000 - 10);
+
+        let (preview, _) = get_file_preview(&path, max_scroll, 10).unwrap();
+        assert_eq!(preview.lines().count(), 10);
+    }
+}
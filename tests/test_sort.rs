@@ -0,0 +1,50 @@
+#[cfg(test)]
+mod tests {
+    use file_manager::fs_utils::FileInfo;
+    use file_manager::sort::*;
+    use std::cmp::Ordering;
+
+    fn file(name: &str, size: u64, is_dir: bool) -> FileInfo {
+        FileInfo {
+            name: name.into(),
+            is_dir,
+            size,
+            ..Default::default()
+        }
+    }
+
+    fn names(files: &[FileInfo]) -> Vec<&str> {
+        files.iter().map(|file_info| file_info.name.to_str().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_natural_cmp() {
+        assert_eq!(natural_cmp("file2", "file10"), Ordering::Less);
+        assert_eq!(natural_cmp("File1", "file2"), Ordering::Less);
+        assert_eq!(natural_cmp("a007", "a7"), Ordering::Less);
+        assert_eq!(natural_cmp("abc", "abc"), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_sort_by_name_dirs_first() {
+        let mut files = vec![file("b10.txt", 0, false), file("zdir", 0, true), file("b9.txt", 0, false)];
+        sort_files(&mut files, &SortOptions::default());
+        assert_eq!(names(&files), vec!["zdir", "b9.txt", "b10.txt"]);
+    }
+
+    #[test]
+    fn test_sort_by_size_reversed() {
+        let mut files = vec![file("small", 1, false), file("big", 100, false), file("dir", 0, true)];
+        let options = SortOptions { mode: SortMode::Size, dirs_first: true, reverse: true };
+        sort_files(&mut files, &options);
+        assert_eq!(names(&files), vec!["dir", "small", "big"]);
+    }
+
+    #[test]
+    fn test_sort_by_extension_without_dirs_first() {
+        let mut files = vec![file("a.txt", 0, false), file("b.rs", 0, false), file("c", 0, true)];
+        let options = SortOptions { mode: SortMode::Extension, dirs_first: false, reverse: false };
+        sort_files(&mut files, &options);
+        assert_eq!(names(&files), vec!["c", "b.rs", "a.txt"]);
+    }
+}
@@ -0,0 +1,75 @@
+#[cfg(test)]
+mod tests {
+    use file_manager::state::Marks;
+    use file_manager::fs_utils::{create_file_info, FileInfo};
+    use tempfile::tempdir;
+    use std::{fs::File, path::{Path, PathBuf}};
+
+    fn file_infos(names: &[&str]) -> Vec<FileInfo> {
+        names.iter().map(|name| create_file_info(name.to_string())).collect()
+    }
+
+    // With marks the targets are exactly the marked paths, whatever is selected
+    fn marked(marks: &Marks) -> Vec<PathBuf> {
+        marks.targets(Path::new("/"), None, &[], false)
+    }
+
+    #[test]
+    fn test_toggle_and_invert_marks() {
+        let dir = PathBuf::from("/project");
+        let files = file_infos(&["a", "b", "c"]);
+        let mut marks = Marks::default();
+
+        marks.toggle(dir.join("a"));
+        assert_eq!(marked(&marks), vec![dir.join("a")]);
+        marks.toggle(dir.join("a"));
+        assert!(marks.is_empty());
+
+        marks.toggle(dir.join("a"));
+        marks.invert(&dir, &files);
+        assert_eq!(marked(&marks), vec![dir.join("b"), dir.join("c")]);
+        assert!(!marks.contains(&dir.join("a")));
+
+        // Marks in other directories are left alone
+        marks.toggle(PathBuf::from("/elsewhere/d"));
+        marks.mark_all(&dir, &files);
+        assert_eq!(marked(&marks).len(), 4);
+        marks.invert(&dir, &files);
+        assert_eq!(marked(&marks), vec![PathBuf::from("/elsewhere/d")]);
+
+        marks.clear();
+        assert!(marks.is_empty());
+    }
+
+    #[test]
+    fn test_targets() {
+        let dir = tempdir().unwrap();
+        File::create(dir.path().join("a")).unwrap();
+        File::create(dir.path().join("b")).unwrap();
+        let files = file_infos(&["a", "b", "gone"]);
+        let mut marks = Marks::default();
+
+        // Without marks it is the selected entry, as long as it still exists
+        assert_eq!(marks.targets(dir.path(), Some(1), &files, false), vec![dir.path().join("b")]);
+        assert!(marks.targets(dir.path(), Some(2), &files, false).is_empty());
+        assert!(marks.targets(dir.path(), None, &files, false).is_empty());
+
+        // Marks win over the selection
+        marks.mark_all(dir.path(), &files[..2]);
+        assert_eq!(marks.targets(dir.path(), Some(2), &files, false), vec![dir.path().join("a"), dir.path().join("b")]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_targets_following_symlinks() {
+        let dir = tempdir().unwrap();
+        let target = dir.path().join("target.txt");
+        File::create(&target).unwrap();
+        std::os::unix::fs::symlink(&target, dir.path().join("link")).unwrap();
+        let files = file_infos(&["link"]);
+
+        let marks = Marks::default();
+        assert_eq!(marks.targets(dir.path(), Some(0), &files, false), vec![dir.path().join("link")]);
+        assert_eq!(marks.targets(dir.path(), Some(0), &files, true), vec![target.canonicalize().unwrap()]);
+    }
+}
@@ -0,0 +1,110 @@
+#[cfg(test)]
+mod tests {
+    use file_manager::tabs::*;
+    use std::path::{Path, PathBuf};
+
+    fn tab(name: &str) -> Tab {
+        Tab {
+            current_dir: PathBuf::from("/").join(name),
+            ..Default::default()
+        }
+    }
+
+    // Open tabs "b", "c", ... after "a", leaving the last one on screen
+    fn open_tabs(names: &[&str]) -> (Tabs, Tab) {
+        let mut tabs = Tabs::default();
+        let mut current = tab(names[0]);
+        for name in &names[1..] {
+            tabs.open(current);
+            current = tab(name);
+        }
+        (tabs, current)
+    }
+
+    fn labels(tabs: &Tabs, current: &Tab) -> Vec<String> {
+        tabs.labels(&current.current_dir).iter().map(|label| label.trim().to_string()).collect()
+    }
+
+    #[test]
+    fn test_open_tab() {
+        let (mut tabs, current) = open_tabs(&["a"]);
+        assert_eq!(tabs.count(), 1);
+        assert_eq!(labels(&tabs, &current), vec!["1:a"]);
+
+        tabs.open(current);
+        let current = tab("b");
+        assert_eq!(tabs.count(), 2);
+        assert_eq!(tabs.active(), 1);
+        assert_eq!(labels(&tabs, &current), vec!["1:a", "2:b"]);
+
+        // A new tab opens right after the one on screen rather than at the end
+        let current = tabs.switch_to(0, current);
+        tabs.open(current);
+        let current = tab("c");
+        assert_eq!(tabs.active(), 1);
+        assert_eq!(labels(&tabs, &current), vec!["1:a", "2:c", "3:b"]);
+    }
+
+    #[test]
+    fn test_close_first_tab() {
+        let (mut tabs, current) = open_tabs(&["a", "b", "c"]);
+        let current = tabs.switch_to(0, current);
+        assert_eq!(current.current_dir, Path::new("/a"));
+
+        let current = tabs.close().unwrap();
+        assert_eq!(current.current_dir, Path::new("/b"));
+        assert_eq!(tabs.active(), 0);
+        assert_eq!(labels(&tabs, &current), vec!["1:b", "2:c"]);
+    }
+
+    #[test]
+    fn test_close_middle_tab() {
+        let (mut tabs, current) = open_tabs(&["a", "b", "c"]);
+        assert_eq!(tabs.switch_to(1, current).current_dir, Path::new("/b"));
+
+        // The tab after it takes its place
+        let current = tabs.close().unwrap();
+        assert_eq!(current.current_dir, Path::new("/c"));
+        assert_eq!(tabs.active(), 1);
+        assert_eq!(labels(&tabs, &current), vec!["1:a", "2:c"]);
+    }
+
+    #[test]
+    fn test_close_last_tab() {
+        let (mut tabs, _) = open_tabs(&["a", "b", "c"]);
+
+        // There is no tab after it, so the one before takes its place
+        let current = tabs.close().unwrap();
+        assert_eq!(current.current_dir, Path::new("/b"));
+        assert_eq!(tabs.active(), 1);
+        assert_eq!(labels(&tabs, &current), vec!["1:a", "2:b"]);
+
+        let current = tabs.close().unwrap();
+        assert_eq!(labels(&tabs, &current), vec!["1:a"]);
+        assert!(tabs.close().is_none());
+    }
+
+    #[test]
+    fn test_next_and_previous_wrap_around() {
+        let (mut tabs, mut current) = open_tabs(&["a", "b", "c"]);
+        assert_eq!(tabs.active(), 2);
+
+        assert_eq!(tabs.next(), 0);
+        current = tabs.switch_to(tabs.next(), current);
+        assert_eq!(current.current_dir, Path::new("/a"));
+        assert_eq!(labels(&tabs, &current), vec!["1:a", "2:b", "3:c"]);
+
+        assert_eq!(tabs.previous(), 2);
+        current = tabs.switch_to(tabs.previous(), current);
+        assert_eq!(current.current_dir, Path::new("/c"));
+
+        current = tabs.switch_to(tabs.previous(), current);
+        assert_eq!(current.current_dir, Path::new("/b"));
+        assert_eq!(labels(&tabs, &current), vec!["1:a", "2:b", "3:c"]);
+
+        // A single tab is its own neighbour
+        let (tabs, _) = open_tabs(&["a"]);
+        assert_eq!(tabs.next(), 0);
+        assert_eq!(tabs.previous(), 0);
+    }
+}
@@ -0,0 +1,98 @@
+#[cfg(test)]
+mod tests {
+    use file_manager::trash::*;
+    use tempfile::tempdir;
+    use std::fs::{self, File};
+
+    #[test]
+    fn test_trash_and_list() {
+        let dir = tempdir().unwrap();
+        let trash = Trash::new(&dir.path().join("Trash"));
+        let file_path = dir.path().join("my file.txt");
+        File::create(&file_path).unwrap();
+
+        let entry = trash.trash(&file_path).unwrap();
+        assert!(!file_path.exists());
+        assert!(trash.files_dir.join(&entry.name).exists());
+
+        let info = fs::read_to_string(trash.info_dir.join("my file.txt.trashinfo")).unwrap();
+        assert!(info.starts_with("[Trash Info]\n"));
+        assert!(info.contains("/my%20file.txt\n"));
+
+        let entries = trash.list().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].original_path, file_path);
+    }
+
+    #[test]
+    fn test_trash_name_collision() {
+        let dir = tempdir().unwrap();
+        let trash = Trash::new(&dir.path().join("Trash"));
+        let file_path = dir.path().join("test.txt");
+
+        File::create(&file_path).unwrap();
+        let first = trash.trash(&file_path).unwrap();
+        File::create(&file_path).unwrap();
+        let second = trash.trash(&file_path).unwrap();
+
+        assert_ne!(first.name, second.name);
+        assert_eq!(trash.list().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_restore() {
+        let dir = tempdir().unwrap();
+        let trash = Trash::new(&dir.path().join("Trash"));
+        let subdir = dir.path().join("subdir");
+        fs::create_dir(&subdir).unwrap();
+        File::create(subdir.join("test.txt")).unwrap();
+
+        let entry = trash.trash(&subdir).unwrap();
+        let restored = trash.restore(&entry).unwrap();
+
+        assert_eq!(restored, subdir);
+        assert!(subdir.join("test.txt").exists());
+        assert!(trash.list().unwrap().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_topdir_trash() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let topdir = tempdir().unwrap();
+        let file_path = topdir.path().join("test.txt");
+        File::create(&file_path).unwrap();
+
+        let trash = Trash::in_topdir(topdir.path()).unwrap();
+        assert_eq!(trash.root.parent(), Some(topdir.path()));
+        assert!(trash.root.file_name().unwrap().to_string_lossy().starts_with(".Trash-"));
+        assert_eq!(fs::metadata(&trash.root).unwrap().permissions().mode() & 0o777, 0o700);
+
+        let entry = trash.trash(&file_path).unwrap();
+        assert_eq!(entry.trash_dir, trash.root);
+        assert_eq!(trash.list().unwrap()[0].original_path, file_path);
+        assert_eq!(Trash::new(&entry.trash_dir).restore(&entry).unwrap(), file_path);
+
+        // A shared .Trash with the sticky bit gets a directory per user instead
+        let shared = topdir.path().join(".Trash");
+        fs::create_dir(&shared).unwrap();
+        fs::set_permissions(&shared, fs::Permissions::from_mode(0o1777)).unwrap();
+        let trash = Trash::in_topdir(topdir.path()).unwrap();
+        assert_eq!(trash.root.parent(), Some(shared.as_path()));
+    }
+
+    #[test]
+    fn test_empty() {
+        let dir = tempdir().unwrap();
+        let trash = Trash::new(&dir.path().join("Trash"));
+        let file_path = dir.path().join("test.txt");
+        File::create(&file_path).unwrap();
+
+        trash.trash(&file_path).unwrap();
+        trash.empty().unwrap();
+
+        assert!(trash.list().unwrap().is_empty());
+        assert_eq!(fs::read_dir(&trash.files_dir).unwrap().count(), 0);
+    }
+}